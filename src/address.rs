@@ -4,6 +4,7 @@ use std::fmt;
 use crate::utils::encode_mime_b;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Address<'a> {
     pub name: Option<Cow<'a, str>>,
     pub email: Cow<'a, str>,
@@ -23,6 +24,13 @@ impl<'a> Address<'a> {
             email: email.into(),
         };
     }
+
+    pub fn into_owned(self) -> Address<'static> {
+        return Address {
+            name: self.name.map(|n| Cow::Owned(n.into_owned())),
+            email: Cow::Owned(self.email.into_owned()),
+        };
+    }
 }
 
 impl fmt::Display for Address<'_> {
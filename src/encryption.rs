@@ -0,0 +1,99 @@
+//! Shared by the OpenPGP and S/MIME mailers.
+
+use std::error::Error;
+
+use crate::Address;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::RawMailer;
+
+/// Encrypts the (rendered) message for the keys of its recipients and sends it on,
+/// or sends it as is when there are no keys at all.
+///
+/// Anyone can tell whose keys a message was encrypted for, so each of the Bcc recipients
+/// gets a copy of their own. Fails before sending anything if a recipient doesn't have a key.
+pub(crate) async fn send_encrypted<K: Sync>(
+    mailer: &impl RawMailer,
+    keys: &[(String, K)],
+    kind: &str,
+    m: &Message<'_>,
+    raw: &str,
+    encrypt: fn(&str, &[&K]) -> Result<String, GenericMailerError>,
+) -> Result<Vec<String>, GenericMailerError> {
+    if keys.is_empty() {
+        return mailer.send_raw(m, raw.as_bytes()).await;
+    }
+
+    let mut copies = Vec::new();
+
+    if !m.to.is_empty() || !m.cc.is_empty() {
+        let mut envelope = m.clone();
+        envelope.bcc.clear();
+        copies.push(envelope);
+    }
+
+    for addr in &m.bcc {
+        let mut envelope = m.clone();
+        envelope.to.clear();
+        envelope.cc.clear();
+        envelope.bcc = vec![addr.clone()];
+        copies.push(envelope);
+    }
+
+    let mut encrypted = Vec::with_capacity(copies.len());
+
+    for envelope in copies {
+        let recipients = envelope.to.iter().chain(&envelope.cc).chain(&envelope.bcc);
+        let copy_keys = find_keys(keys, kind, recipients, &envelope.from)?;
+        encrypted.push((encrypt(raw, &copy_keys)?, envelope));
+    }
+
+    let mut ids = Vec::new();
+
+    for (raw, envelope) in encrypted {
+        ids.extend(mailer.send_raw(&envelope, raw.as_bytes()).await?);
+    }
+
+    return Ok(ids);
+}
+
+// The key of every recipient, and of the sender so that they can read their own copy
+// (e.g. in the sent folder), each key only once
+fn find_keys<'k, 'a, K>(
+    keys: &'k [(String, K)],
+    kind: &str,
+    recipients: impl Iterator<Item = &'a Address<'a>>,
+    sender: &Address,
+) -> Result<Vec<&'k K>, GenericMailerError> {
+    let find_key = |email: &str| {
+        return keys
+            .iter()
+            .find(|(e, _)| e.eq_ignore_ascii_case(email))
+            .map(|(_, key)| key);
+    };
+
+    let mut found: Vec<&K> = Vec::new();
+
+    for addr in recipients {
+        let Some(key) = find_key(&addr.email) else {
+            let error = format!("no {kind} for {}", addr.email);
+            return Err(GenericMailerError::UnexpectedError(error.into()));
+        };
+
+        if !found.iter().any(|k| std::ptr::eq(*k, key)) {
+            found.push(key);
+        }
+    }
+
+    if let Some(key) = find_key(&sender.email)
+        && !found.iter().any(|k| std::ptr::eq(*k, key))
+    {
+        found.push(key);
+    }
+
+    return Ok(found);
+}
+
+pub(crate) fn unexpected_error(err: impl Error + 'static) -> GenericMailerError {
+    return GenericMailerError::UnexpectedError(Box::new(err));
+}
@@ -10,18 +10,42 @@ pub trait GenericMailer: Send + Sync {
     async fn send(&self, message: &Message) -> Result<Vec<String>, GenericMailerError>;
 }
 
+/// A transport that sends messages already rendered as MIME, e.g. after signing them.
+#[async_trait]
+pub trait RawMailer: Send + Sync {
+    /// The message is only used for the envelope, i.e. the sender and recipients (including `bcc`).
+    async fn send_raw(
+        &self,
+        message: &Message,
+        raw: &[u8],
+    ) -> Result<Vec<String>, GenericMailerError>;
+}
+
 #[derive(Debug)]
 pub enum GenericMailerError {
     UnexpectedResponse(u16, String),
+    ProviderError(u16, ProviderError),
     UnexpectedError(Box<dyn Error>),
 }
 
+// An error reported by the provider in a format we understand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderError {
+    pub code: Option<String>,
+    pub message: String,
+    /// Per-field validation errors, as `(field, message)` pairs
+    pub details: Vec<(String, String)>,
+}
+
 impl fmt::Display for GenericMailerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         return match self {
             GenericMailerError::UnexpectedResponse(status, body) => {
                 write!(f, "Unexpected response: {status} - {body}")
             }
+            GenericMailerError::ProviderError(status, error) => {
+                write!(f, "Provider error: {status} - {error}")
+            }
             GenericMailerError::UnexpectedError(error) => write!(f, "Unexpected error: {error}"),
         };
     }
@@ -29,6 +53,27 @@ impl fmt::Display for GenericMailerError {
 
 impl Error for GenericMailerError {}
 
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(code) = &self.code {
+            write!(f, "[{code}] ")?;
+        }
+
+        write!(f, "{}", self.message)?;
+
+        for (i, (field, message)) in self.details.iter().enumerate() {
+            let separator = if i == 0 { " (" } else { ", " };
+            write!(f, "{separator}{field}: {message}")?;
+        }
+
+        if !self.details.is_empty() {
+            write!(f, ")")?;
+        }
+
+        return Ok(());
+    }
+}
+
 impl From<std::io::Error> for GenericMailerError {
     fn from(err: std::io::Error) -> Self {
         return GenericMailerError::UnexpectedError(Box::new(err));
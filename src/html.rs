@@ -0,0 +1,623 @@
+//! Helpers for working with HTML bodies.
+
+use std::borrow::Cow;
+
+/// Derives a readable plain-text version of an HTML document, e.g. for the text body.
+///
+/// - Headings are prefixed with `#`, e.g. `## Heading`
+/// - List items are prefixed with `*` or their number, and indented when nested
+/// - Table rows are put on their own line, with the cells separated by `|`
+/// - Links are numbered, with their URLs listed as footnotes at the end
+///
+/// This is a best effort conversion, meant for the kind of HTML found in emails.
+pub fn to_text(html: &str) -> String {
+    let mut writer = TextWriter::default();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => writer.text(&decode_entities(text)),
+            Token::StartTag(name, attrs) => writer.start_tag(&name, &attrs),
+            Token::EndTag(name) => writer.end_tag(&name),
+        }
+    }
+
+    return writer.finish();
+}
+
+/// Options for `inline_css`.
+#[cfg(feature = "css_inline")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InlineCssOptions {
+    /// Keeps `@media` queries (and other at-rules) in a `<style>` block, since they can't be inlined.
+    pub keep_media_queries: bool,
+}
+
+/// Moves the rules in `<style>` blocks into `style` attributes, since clients like
+/// Gmail and Outlook strip the blocks out. Linked stylesheets are left as is.
+#[cfg(feature = "css_inline")]
+pub fn inline_css(
+    html: &str,
+    options: InlineCssOptions,
+) -> Result<String, css_inline::InlineError> {
+    let inliner = css_inline::CSSInliner::options()
+        .keep_at_rules(options.keep_media_queries)
+        .load_remote_stylesheets(false)
+        .build();
+
+    return inliner.inline(html);
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    StartTag(String, Vec<(String, String)>),
+    EndTag(String),
+}
+
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+
+        if lt > 0 {
+            tokens.push(Token::Text(&rest[..lt]));
+            rest = &rest[lt..];
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |i| &comment[i + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |i| &rest[i + 1..]);
+        } else if let Some((token, after)) = parse_tag(rest) {
+            rest = after;
+
+            // Their contents are raw text, which may contain `<`, and never shown anyway
+            if let Token::StartTag(name, _) = &token
+                && (name == "script" || name == "style")
+            {
+                rest = skip_raw_text(rest, name);
+                continue;
+            }
+
+            tokens.push(token);
+        } else {
+            tokens.push(Token::Text("<"));
+            rest = &rest[1..];
+        }
+    }
+
+    return tokens;
+}
+
+fn parse_tag(s: &str) -> Option<(Token<'_>, &str)> {
+    let end = find_tag_end(s)?;
+    let (inner, rest) = (&s[1..end], &s[end + 1..]);
+
+    let (is_end_tag, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+
+    if !inner.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let name_len = inner
+        .find(|c: char| c.is_whitespace() || c == '/')
+        .unwrap_or(inner.len());
+    let name = inner[..name_len].to_ascii_lowercase();
+
+    if is_end_tag {
+        return Some((Token::EndTag(name), rest));
+    }
+
+    return Some((Token::StartTag(name, parse_attrs(&inner[name_len..])), rest));
+}
+
+fn find_tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i),
+            None => {}
+        }
+    }
+
+    return None;
+}
+
+fn parse_attrs(s: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let is_separator = |c: char| c.is_whitespace() || c == '/';
+    let mut rest = s.trim_start_matches(is_separator);
+
+    while !rest.is_empty() {
+        let name_len = rest
+            .find(|c: char| is_separator(c) || c == '=')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let mut value = String::new();
+
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+
+            let (raw, after) = match after_eq.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let quoted = &after_eq[1..];
+                    let end = quoted.find(q).unwrap_or(quoted.len());
+                    (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(is_separator).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+
+            value = decode_entities(raw).into_owned();
+            rest = after;
+        }
+
+        attrs.push((name, value));
+        rest = rest.trim_start_matches(is_separator);
+    }
+
+    return attrs;
+}
+
+fn skip_raw_text<'a>(s: &'a str, name: &str) -> &'a str {
+    // Lowercasing ASCII keeps the byte offsets the same
+    let Some(start) = s.to_ascii_lowercase().find(&format!("</{name}")) else {
+        return "";
+    };
+
+    return s[start..].find('>').map_or("", |i| &s[start + i + 1..]);
+}
+
+fn decode_entities(s: &str) -> Cow<'_, str> {
+    if !s.contains('&') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&i| i <= 10)
+            .and_then(|i| Some((decode_entity(&rest[1..i + 1])?, i + 2)));
+
+        match decoded {
+            Some((c, len)) => {
+                result.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    return Cow::Owned(result);
+}
+
+// The HTML 4 Latin-1 entities, i.e. U+00A0 to U+00FF in order
+const LATIN1_ENTITIES: [&str; 96] = [
+    "nbsp", "iexcl", "cent", "pound", "curren", "yen", "brvbar", "sect", "uml", "copy", "ordf",
+    "laquo", "not", "shy", "reg", "macr", "deg", "plusmn", "sup2", "sup3", "acute", "micro",
+    "para", "middot", "cedil", "sup1", "ordm", "raquo", "frac14", "frac12", "frac34", "iquest",
+    "Agrave", "Aacute", "Acirc", "Atilde", "Auml", "Aring", "AElig", "Ccedil", "Egrave", "Eacute",
+    "Ecirc", "Euml", "Igrave", "Iacute", "Icirc", "Iuml", "ETH", "Ntilde", "Ograve", "Oacute",
+    "Ocirc", "Otilde", "Ouml", "times", "Oslash", "Ugrave", "Uacute", "Ucirc", "Uuml", "Yacute",
+    "THORN", "szlig", "agrave", "aacute", "acirc", "atilde", "auml", "aring", "aelig", "ccedil",
+    "egrave", "eacute", "ecirc", "euml", "igrave", "iacute", "icirc", "iuml", "eth", "ntilde",
+    "ograve", "oacute", "ocirc", "otilde", "ouml", "divide", "oslash", "ugrave", "uacute", "ucirc",
+    "uuml", "yacute", "thorn", "yuml",
+];
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+
+        return char::from_u32(code);
+    }
+
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        "euro" => '€',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bull" => '•',
+        _ => {
+            let i = LATIN1_ENTITIES.iter().position(|&n| n == name)?;
+            return char::from_u32(0xA0 + i as u32);
+        }
+    };
+
+    return Some(c);
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    return attrs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str());
+}
+
+enum ListKind {
+    Unordered,
+    Ordered(usize),
+}
+
+// Whitespace and line breaks are kept pending until the next text is written,
+// so that they collapse like they would in a browser.
+#[derive(Default)]
+struct TextWriter {
+    out: String,
+    line_started: bool,
+    pending_breaks: usize,
+    pending_space: bool,
+    pending_separator: bool,
+    pending_marker: Option<String>,
+    lists: Vec<ListKind>,
+    quote_depth: usize,
+    pre_depth: usize,
+    skip_depth: usize,
+    links: Vec<Option<(String, usize)>>,
+    footnotes: Vec<String>,
+}
+
+impl TextWriter {
+    fn start_tag(&mut self, name: &str, attrs: &[(String, String)]) {
+        if name == "head" || name == "title" {
+            self.skip_depth += 1;
+        }
+
+        if self.skip_depth > 0 {
+            return;
+        }
+
+        match name {
+            "br" => self.line_break(),
+            "hr" => {
+                self.block(2);
+                self.write("---");
+                self.block(2);
+            }
+            "p" | "table" => self.block(2),
+            "div" | "tr" | "center" | "section" | "article" | "header" | "footer" => self.block(1),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = (name.as_bytes()[1] - b'0') as usize;
+
+                self.block(2);
+                self.write(&"#".repeat(level));
+                self.pending_space = true;
+            }
+            "blockquote" => {
+                self.block(2);
+                self.quote_depth += 1;
+            }
+            "pre" => {
+                self.block(2);
+                self.pre_depth += 1;
+            }
+            "ul" | "ol" => {
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+
+                let kind = match name {
+                    "ol" => ListKind::Ordered(
+                        attr(attrs, "start")
+                            .and_then(|s| s.trim().parse().ok())
+                            .unwrap_or(1),
+                    ),
+                    _ => ListKind::Unordered,
+                };
+
+                self.lists.push(kind);
+            }
+            "li" => {
+                self.block(1);
+
+                let marker = match self.lists.last_mut() {
+                    Some(ListKind::Ordered(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "* ".to_string(),
+                };
+
+                self.pending_marker = Some(marker);
+            }
+            // Cells on the same line are separated, while those with block content aren't
+            "td" | "th" if self.line_started && self.pending_breaks == 0 => {
+                self.pending_separator = true;
+            }
+            "a" => {
+                let href = attr(attrs, "href").map(|href| (href.to_string(), self.out.len()));
+                self.links.push(href);
+            }
+            "img" => {
+                if let Some(alt) = attr(attrs, "alt") {
+                    self.text(alt);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        if name == "head" || name == "title" {
+            self.skip_depth = self.skip_depth.saturating_sub(1);
+            return;
+        }
+
+        if self.skip_depth > 0 {
+            return;
+        }
+
+        match name {
+            "p" | "table" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.block(2),
+            "div" | "tr" | "li" | "center" | "section" | "article" | "header" | "footer" => {
+                self.block(1)
+            }
+            "blockquote" => {
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.block(2);
+            }
+            "pre" => {
+                self.pre_depth = self.pre_depth.saturating_sub(1);
+                self.block(2);
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.block(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "a" => {
+                if let Some(Some((href, start))) = self.links.pop() {
+                    self.footnote(&href, start);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skip_depth > 0 {
+            return;
+        }
+
+        if self.pre_depth > 0 {
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.line_break();
+                }
+
+                if !line.is_empty() {
+                    self.write(line);
+                }
+            }
+
+            return;
+        }
+
+        if text.starts_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+
+        for (i, word) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                self.pending_space = true;
+            }
+
+            self.write(word);
+        }
+
+        if text.ends_with(char::is_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    fn footnote(&mut self, href: &str, start: usize) {
+        let url = href.trim();
+
+        if url.is_empty() || url.starts_with('#') || url.starts_with("javascript:") {
+            return;
+        }
+
+        // No need to repeat the URL when it's already the link text
+        let text = self.out[start..].trim();
+        if text == url || url.strip_prefix("mailto:") == Some(text) {
+            return;
+        }
+
+        let n = match self.footnotes.iter().position(|f| f == url) {
+            Some(i) => i + 1,
+            None => {
+                self.footnotes.push(url.to_string());
+                self.footnotes.len()
+            }
+        };
+
+        self.pending_space = true;
+        self.write(&format!("[{n}]"));
+    }
+
+    fn line_break(&mut self) {
+        self.pending_breaks = (self.pending_breaks + 1).min(2);
+        self.pending_space = false;
+    }
+
+    fn block(&mut self, breaks: usize) {
+        self.pending_breaks = self.pending_breaks.max(breaks);
+        self.pending_space = false;
+    }
+
+    fn write(&mut self, s: &str) {
+        if self.pending_breaks > 0 {
+            if !self.out.is_empty() {
+                self.out.push_str(&"\n".repeat(self.pending_breaks));
+            }
+
+            self.pending_breaks = 0;
+            self.line_started = false;
+        }
+
+        if !self.line_started {
+            self.write_prefix();
+            self.line_started = true;
+        } else if self.pending_separator {
+            self.out.push_str(" | ");
+        } else if self.pending_space {
+            self.out.push(' ');
+        }
+
+        self.pending_space = false;
+        self.pending_separator = false;
+        self.out.push_str(s);
+    }
+
+    fn write_prefix(&mut self) {
+        for _ in 0..self.quote_depth {
+            self.out.push_str("> ");
+        }
+
+        if !self.lists.is_empty() {
+            self.out.push_str(&"  ".repeat(self.lists.len() - 1));
+        }
+
+        match self.pending_marker.take() {
+            Some(marker) => self.out.push_str(&marker),
+            None if !self.lists.is_empty() => self.out.push_str("  "),
+            None => {}
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = self.out;
+
+        if !self.footnotes.is_empty() {
+            text.push_str("\n\n");
+
+            for (i, url) in self.footnotes.iter().enumerate() {
+                text.push_str(&format!("[{}] {url}\n", i + 1));
+            }
+        }
+
+        return text.trim_end().to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head><title>Ignored</title><style>p { color: red; }</style></head>
+<body>
+  <h1>Welcome,   Ann!</h1>
+  <p>Thanks for signing up.<br>Please <a href="https://example.com/confirm?a=1&amp;b=2">confirm your email</a>
+  or visit <a href="https://example.com">https://example.com</a>.</p>
+  <!-- A comment -->
+  <ul>
+    <li>First</li>
+    <li>Second
+      <ol start="3"><li>Nested</li><li>Also nested</li></ol>
+    </li>
+  </ul>
+  <table>
+    <tr><th>Item</th><th>Price</th></tr>
+    <tr><td>Tea &amp; cake</td><td>&pound;5</td></tr>
+  </table>
+  <blockquote>Quoted text</blockquote>
+  <p><a href="https://example.com/confirm?a=1&b=2"><img src="logo.png" alt="Logo"></a></p>
+</body>
+</html>"#;
+
+        let expected = "\
+# Welcome, Ann!
+
+Thanks for signing up.
+Please confirm your email [1] or visit https://example.com.
+
+* First
+* Second
+  3. Nested
+  4. Also nested
+
+Item | Price
+Tea & cake | £5
+
+> Quoted text
+
+Logo [1]
+
+[1] https://example.com/confirm?a=1&b=2";
+
+        assert_eq!(to_text(html), expected);
+    }
+
+    #[test]
+    fn test_html_to_text_pre() {
+        let html = "<p>Run:</p><pre>cargo  build\ncargo test</pre><p>Done &lt;3</p>";
+
+        assert_eq!(to_text(html), "Run:\n\ncargo  build\ncargo test\n\nDone <3");
+    }
+
+    #[cfg(feature = "css_inline")]
+    #[test]
+    fn test_inline_css() {
+        let html = "<html><head><style>\
+            p { color: red; } \
+            @media (max-width: 600px) { p { color: blue; } }\
+            </style></head><body><p>Hello</p></body></html>";
+
+        let actual = inline_css(html, InlineCssOptions::default()).unwrap();
+        assert_eq!(
+            actual,
+            "<html><head></head><body><p style=\"color: red;\">Hello</p></body></html>"
+        );
+
+        let options = InlineCssOptions {
+            keep_media_queries: true,
+        };
+        let actual = inline_css(html, options).unwrap();
+        assert_eq!(
+            actual,
+            "<html><head><style>@media (max-width: 600px) { p { color: blue; } } </style></head>\
+            <body><p style=\"color: red;\">Hello</p></body></html>"
+        );
+    }
+}
@@ -2,11 +2,29 @@ mod address;
 mod generic_mailer;
 mod message;
 
+#[cfg(feature = "dkim")]
+pub mod dkim;
+#[cfg(any(feature = "pgp", feature = "smime"))]
+mod encryption;
+pub mod html;
 pub mod mailers;
+pub mod mime;
+#[cfg(feature = "outbox")]
+pub mod outbox;
+#[cfg(feature = "pgp")]
+pub mod pgp;
+#[cfg(feature = "smime")]
+pub mod smime;
+pub mod templates;
 pub mod utils;
 
 pub use address::Address;
 pub use generic_mailer::GenericMailer;
 pub use generic_mailer::GenericMailerError;
+pub use generic_mailer::ProviderError;
+pub use generic_mailer::RawMailer;
 pub use message::Message;
+pub use message::MessageAttachment;
 pub use message::MessageBuilder;
+pub use message::MessageTemplate;
+pub use message::TemplateValue;
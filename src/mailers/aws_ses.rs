@@ -1,22 +1,51 @@
+use std::borrow::Cow;
+
 use async_trait::async_trait;
+use aws_sdk_sesv2::operation::send_email::builders::SendEmailFluentBuilder;
+use aws_sdk_sesv2::primitives::Blob;
 use aws_sdk_sesv2::types::Body;
+use aws_sdk_sesv2::types::BulkEmailContent;
+use aws_sdk_sesv2::types::BulkEmailEntry;
+use aws_sdk_sesv2::types::BulkEmailStatus;
 use aws_sdk_sesv2::types::Content;
 use aws_sdk_sesv2::types::Destination;
 use aws_sdk_sesv2::types::EmailContent;
 use aws_sdk_sesv2::types::Message as SesMessage;
 use aws_sdk_sesv2::types::MessageHeader;
 use aws_sdk_sesv2::types::MessageTag;
+use aws_sdk_sesv2::types::RawMessage;
+use aws_sdk_sesv2::types::ReplacementEmailContent;
+use aws_sdk_sesv2::types::ReplacementTemplate;
+use aws_sdk_sesv2::types::Template;
 
 use crate::Address;
 use crate::GenericMailer;
 use crate::GenericMailerError;
 use crate::Message;
+use crate::MessageTemplate;
+use crate::RawMailer;
+use crate::TemplateValue;
+use crate::mailers::sanitize_tags;
+use crate::mime::render_message;
 
 pub struct AwsSesMailer {
-    pub client: aws_sdk_sesv2::Client,
-    pub configuration_set: Option<String>,
-    pub sender_identity_arn: String,
-    pub feedback_forwarding: Option<AwsSesFeedbackForwarding>,
+    client: aws_sdk_sesv2::Client,
+    configuration_set: Option<String>,
+    sender_identity_arn: Option<String>,
+    feedback_forwarding: Option<AwsSesFeedbackForwarding>,
+    default_tags: Vec<(String, String)>,
+    content_mode: AwsSesContentMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AwsSesContentMode {
+    /// Sends raw MIME only when the message has attachments
+    #[default]
+    Auto,
+    /// Lets SES build the MIME message from the subject and body
+    Simple,
+    /// Renders the MIME message ourselves, see `gen_mailer::mime::render_message`
+    Raw,
 }
 
 pub struct AwsSesFeedbackForwarding {
@@ -24,10 +53,106 @@ pub struct AwsSesFeedbackForwarding {
     pub email: Address<'static>,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct AwsSesBulkDestination<'a> {
+    pub to: Vec<Address<'a>>,
+    pub cc: Vec<Address<'a>>,
+    pub bcc: Vec<Address<'a>>,
+    /// Overrides the template's variables for this destination, unless empty
+    pub variables: Vec<(Cow<'a, str>, TemplateValue<'a>)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AwsSesBulkResult {
+    pub status: Option<BulkEmailStatus>,
+    pub message_id: Option<String>,
+    pub error: Option<String>,
+}
+
 #[async_trait]
 impl GenericMailer for AwsSesMailer {
     async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
-        let mut builder = self.client.send_email();
+        let content = if let Some(template) = &m.template {
+            Self::build_template_content(template)
+        } else if self.use_raw_content(m) {
+            Self::build_raw_content(m)
+        } else {
+            Self::build_content(m)
+        };
+
+        return self.send_content(m, content).await;
+    }
+}
+
+#[async_trait]
+impl RawMailer for AwsSesMailer {
+    async fn send_raw(&self, m: &Message, raw: &[u8]) -> Result<Vec<String>, GenericMailerError> {
+        return self.send_content(m, build_raw(raw.to_vec())).await;
+    }
+}
+
+impl AwsSesMailer {
+    pub fn new(client: aws_sdk_sesv2::Client) -> Self {
+        Self {
+            client,
+            configuration_set: None,
+            sender_identity_arn: None,
+            feedback_forwarding: None,
+            default_tags: Vec::new(),
+            content_mode: AwsSesContentMode::default(),
+        }
+    }
+
+    /// Loads the region and credentials from the environment, see `aws_config::load_defaults`
+    pub async fn from_env() -> Self {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        return Self::new(aws_sdk_sesv2::Client::new(&config));
+    }
+
+    pub fn configuration_set(mut self, configuration_set: impl Into<String>) -> Self {
+        self.configuration_set = Some(configuration_set.into());
+
+        return self;
+    }
+
+    // Only needed when sending on behalf of an identity owned by another account
+    pub fn sender_identity_arn(mut self, sender_identity_arn: impl Into<String>) -> Self {
+        self.sender_identity_arn = Some(sender_identity_arn.into());
+
+        return self;
+    }
+
+    pub fn feedback_forwarding(mut self, feedback_forwarding: AwsSesFeedbackForwarding) -> Self {
+        self.feedback_forwarding = Some(feedback_forwarding);
+
+        return self;
+    }
+
+    /// Adds a tag to every email, before the message's own category and metadata tags
+    pub fn default_tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.default_tags.push((name.into(), value.into()));
+
+        return self;
+    }
+
+    pub fn content_mode(mut self, content_mode: AwsSesContentMode) -> Self {
+        self.content_mode = content_mode;
+
+        return self;
+    }
+
+    /// Sends one template stored in SES to many destinations, each with its own variables.
+    /// The results are in the same order as the destinations.
+    // See: https://docs.aws.amazon.com/ses/latest/dg/send-personalized-email-api.html
+    pub async fn send_bulk(
+        &self,
+        from: &Address<'_>,
+        reply_to: Option<&Address<'_>>,
+        template: &MessageTemplate<'_>,
+        destinations: &[AwsSesBulkDestination<'_>],
+    ) -> Result<Vec<AwsSesBulkResult>, GenericMailerError> {
+        let mut builder = self.client.send_bulk_email();
 
         if let Some(config) = &self.configuration_set {
             builder = builder.configuration_set_name(config);
@@ -40,65 +165,156 @@ impl GenericMailer for AwsSesMailer {
         }
 
         builder = builder
-            .from_email_address_identity_arn(&self.sender_identity_arn)
-            .from_email_address(m.from.to_string());
+            .set_from_email_address_identity_arn(self.sender_identity_arn.clone())
+            .from_email_address(from.to_string());
 
-        if let Some(reply_to) = &m.reply_to {
+        if let Some(reply_to) = reply_to {
             builder = builder.reply_to_addresses(reply_to.to_string());
         }
 
-        let destination = Self::build_destination(m);
-        builder = builder.destination(destination);
-
-        let content = Self::build_content(m);
-        builder = builder.content(content);
+        let content = BulkEmailContent::builder()
+            .template(build_template(template))
+            .build();
+        builder = builder.default_content(content);
 
-        if m.category.is_some() || !m.headers.is_empty() {
-            let mut tags = vec![];
+        let entries = destinations.iter().map(Self::build_bulk_entry).collect();
+        builder = builder.set_bulk_email_entries(Some(entries));
 
-            if let Some(category) = &m.category {
-                tags.push(build_tag("category", category));
-            }
+        let default_tags = self
+            .default_tags
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()));
+        let tags = sanitize_tags(default_tags)?;
 
-            tags.reserve(m.headers.len());
-            for (k, v) in &m.headers {
-                tags.push(build_tag(k, v));
-            }
-
-            builder = builder.set_email_tags(Some(tags));
+        if !tags.is_empty() {
+            let tags = tags.iter().map(|(k, v)| build_tag(k, v));
+            builder = builder.set_default_email_tags(Some(tags.collect()));
         }
 
         let response = builder.send().await?;
 
+        let results = response
+            .bulk_email_entry_results
+            .into_iter()
+            .map(|r| AwsSesBulkResult {
+                status: r.status,
+                message_id: r.message_id,
+                error: r.error,
+            })
+            .collect();
+
+        return Ok(results);
+    }
+
+    async fn send_content(
+        &self,
+        m: &Message<'_>,
+        content: EmailContent,
+    ) -> Result<Vec<String>, GenericMailerError> {
+        let request = self.build_send_email(m, content)?;
+        let response = request.send().await?;
+
         return Ok(response.message_id.into_iter().collect());
     }
-}
 
-impl AwsSesMailer {
-    fn build_destination(m: &Message) -> Destination {
+    fn build_send_email(
+        &self,
+        m: &Message,
+        content: EmailContent,
+    ) -> Result<SendEmailFluentBuilder, GenericMailerError> {
+        let mut builder = self.client.send_email();
+
+        if let Some(config) = &self.configuration_set {
+            builder = builder.configuration_set_name(config);
+        }
+
+        if let Some(ff) = &self.feedback_forwarding {
+            builder = builder
+                .feedback_forwarding_email_address_identity_arn(&ff.identity_arn)
+                .feedback_forwarding_email_address(ff.email.to_string())
+        }
+
+        builder = builder
+            .set_from_email_address_identity_arn(self.sender_identity_arn.clone())
+            .from_email_address(m.from.to_string());
+
+        if let Some(reply_to) = &m.reply_to {
+            builder = builder.reply_to_addresses(reply_to.to_string());
+        }
+
+        let destination = Self::build_destination(&m.to, &m.cc, &m.bcc);
+        builder = builder.destination(destination).content(content);
+
+        // NOTE: Like the other mailers, the metadata is what gets echoed back
+        // in the events (as tags here), the headers are only sent as headers.
+        let default_tags = self
+            .default_tags
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()));
+        let category = m.category.as_deref().map(|category| ("category", category));
+        let metadata = m.metadata.iter().map(|(k, v)| (k.as_ref(), v.as_ref()));
+        let tags = sanitize_tags(default_tags.chain(category).chain(metadata))?;
+
+        if !tags.is_empty() {
+            let tags = tags.iter().map(|(k, v)| build_tag(k, v));
+            builder = builder.set_email_tags(Some(tags.collect()));
+        }
+
+        return Ok(builder);
+    }
+
+    fn use_raw_content(&self, m: &Message) -> bool {
+        return match self.content_mode {
+            AwsSesContentMode::Auto => !m.attachments.is_empty(),
+            AwsSesContentMode::Simple => false,
+            AwsSesContentMode::Raw => true,
+        };
+    }
+
+    fn build_destination(to: &[Address], cc: &[Address], bcc: &[Address]) -> Destination {
         let mut builder = Destination::builder();
 
-        let to = m.to.iter().map(|m| m.to_string()).collect();
+        let to = to.iter().map(|a| a.to_string()).collect();
         builder = builder.set_to_addresses(Some(to));
 
-        if !m.cc.is_empty() {
-            let cc = m.cc.iter().map(|m| m.to_string()).collect();
+        if !cc.is_empty() {
+            let cc = cc.iter().map(|a| a.to_string()).collect();
             builder = builder.set_cc_addresses(Some(cc));
         }
 
-        if !m.bcc.is_empty() {
-            let bcc = m.bcc.iter().map(|m| m.to_string()).collect();
+        if !bcc.is_empty() {
+            let bcc = bcc.iter().map(|a| a.to_string()).collect();
             builder = builder.set_bcc_addresses(Some(bcc));
         }
 
         return builder.build();
     }
 
+    fn build_bulk_entry(d: &AwsSesBulkDestination) -> BulkEmailEntry {
+        let destination = Self::build_destination(&d.to, &d.cc, &d.bcc);
+        let mut builder = BulkEmailEntry::builder().destination(destination);
+
+        if !d.variables.is_empty() {
+            let data = TemplateValue::map_to_json(&d.variables).to_string();
+            let template = ReplacementTemplate::builder()
+                .replacement_template_data(data)
+                .build();
+            let content = ReplacementEmailContent::builder()
+                .replacement_template(template)
+                .build();
+            builder = builder.replacement_email_content(content);
+        }
+
+        return builder.build();
+    }
+
     fn build_content(m: &Message) -> EmailContent {
         let mut builder = SesMessage::builder();
 
-        if !m.headers.is_empty() {
-            let headers = m.headers.iter().map(|(k, v)| build_header(k, v)).collect();
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let headers = headers.iter().map(|(k, v)| build_header(k, v)).collect();
             builder = builder.set_headers(Some(headers));
         }
 
@@ -109,6 +325,19 @@ impl AwsSesMailer {
         return EmailContent::builder().simple(builder.build()).build();
     }
 
+    // NOTE: The subject and body are ignored, SES renders them from the stored template
+    fn build_template_content(template: &MessageTemplate) -> EmailContent {
+        return EmailContent::builder()
+            .template(build_template(template))
+            .build();
+    }
+
+    // NOTE: The headers are part of the rendered message, and since
+    // the destination is always set, the `Bcc` recipients still get it.
+    fn build_raw_content(m: &Message) -> EmailContent {
+        return build_raw(render_message(m).into_bytes());
+    }
+
     fn build_body(m: &Message) -> Body {
         let mut arnold = Body::builder();
 
@@ -124,6 +353,22 @@ impl AwsSesMailer {
     }
 }
 
+fn build_raw(data: Vec<u8>) -> EmailContent {
+    let raw = RawMessage::builder()
+        .data(Blob::new(data))
+        .build()
+        .expect("Data should be set");
+
+    return EmailContent::builder().raw(raw).build();
+}
+
+fn build_template(template: &MessageTemplate) -> Template {
+    return Template::builder()
+        .template_name(template.id.as_ref())
+        .template_data(template.variables_json().to_string())
+        .build();
+}
+
 fn build_tag(k: &str, v: &str) -> MessageTag {
     return MessageTag::builder()
         .name(k)
@@ -147,3 +392,280 @@ fn encode_string(s: &str) -> Content {
         .build()
         .expect("Data should be set");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use aws_sdk_sesv2::config::BehaviorVersion;
+    use aws_sdk_sesv2::config::Credentials;
+    use aws_sdk_sesv2::config::Region;
+    use aws_smithy_http_client::test_util::infallible_client_fn;
+
+    use super::*;
+    use crate::MessageAttachment;
+
+    fn mailer(content_mode: AwsSesContentMode) -> AwsSesMailer {
+        let config = aws_sdk_sesv2::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .build();
+
+        return AwsSesMailer::new(aws_sdk_sesv2::Client::from_conf(config))
+            .content_mode(content_mode);
+    }
+
+    // A client answering every request with the given body, and the last request body
+    fn stubbed_client(response: &'static str) -> (aws_sdk_sesv2::Client, Arc<Mutex<String>>) {
+        let request_body = Arc::new(Mutex::new(String::new()));
+
+        let captured_body = request_body.clone();
+        let http_client = infallible_client_fn(move |req| {
+            let body = std::str::from_utf8(req.body().bytes().unwrap()).unwrap();
+            *captured_body.lock().unwrap() = body.to_string();
+
+            http::Response::builder()
+                .status(200)
+                .body(response)
+                .unwrap()
+        });
+
+        let config = aws_sdk_sesv2::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("akid", "secret", None, None, "test"))
+            .http_client(http_client)
+            .build();
+
+        return (aws_sdk_sesv2::Client::from_conf(config), request_body);
+    }
+
+    #[tokio::test]
+    async fn test_aws_ses_mailer_send() {
+        let (client, request_body) = stubbed_client(r#"{"MessageId":"0100018f-example"}"#);
+
+        let mailer = AwsSesMailer::new(client)
+            .configuration_set("transactional")
+            .default_tag("app", "gen_mailer");
+
+        let message = Message::builder()
+            .category("welcome")
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let ids = mailer.send(&message).await.unwrap();
+        assert_eq!(ids, ["0100018f-example"]);
+
+        let body = request_body.lock().unwrap();
+        assert!(body.contains(r#""ConfigurationSetName":"transactional""#));
+        assert!(body.contains(r#""FromEmailAddress":"sender@example.com""#));
+        assert!(!body.contains("FromEmailAddressIdentityArn"));
+        assert!(body.contains(
+            r#""EmailTags":[{"Name":"app","Value":"gen_mailer"},{"Name":"category","Value":"welcome"}]"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_aws_ses_mailer_send_template() {
+        let (client, request_body) = stubbed_client(r#"{"MessageId":"0100018f-example"}"#);
+
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .template(MessageTemplate::new("welcome").variable("name", "Recipient"))
+            .build()
+            .unwrap();
+
+        let ids = AwsSesMailer::new(client).send(&message).await.unwrap();
+        assert_eq!(ids, ["0100018f-example"]);
+
+        let body = request_body.lock().unwrap();
+        assert!(body.contains(r#""TemplateName":"welcome""#));
+        assert!(body.contains(r#""TemplateData":"{\"name\":\"Recipient\"}""#));
+        assert!(!body.contains("Simple"));
+    }
+
+    #[tokio::test]
+    async fn test_aws_ses_mailer_send_bulk() {
+        let (client, request_body) = stubbed_client(
+            r#"{"BulkEmailEntryResults":[
+                {"Status":"SUCCESS","MessageId":"0100018f-first"},
+                {"Status":"MESSAGE_REJECTED","Error":"Email address is not verified."}
+            ]}"#,
+        );
+
+        let mailer = AwsSesMailer::new(client).default_tag("app", "gen_mailer");
+        let template = MessageTemplate::new("welcome").variable("name", "Someone");
+        let destinations = [
+            AwsSesBulkDestination {
+                to: vec![Address::new("first@example.com")],
+                variables: vec![("name".into(), TemplateValue::String("First".into()))],
+                ..Default::default()
+            },
+            AwsSesBulkDestination {
+                to: vec![Address::new("second@example.com")],
+                ..Default::default()
+            },
+        ];
+
+        let from = Address::new("sender@example.com");
+        let results = mailer
+            .send_bulk(&from, None, &template, &destinations)
+            .await
+            .unwrap();
+
+        // NOTE: A rejected entry doesn't fail the call, it's only reported in its result
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, Some(BulkEmailStatus::Success));
+        assert_eq!(results[0].message_id.as_deref(), Some("0100018f-first"));
+        assert_eq!(results[1].status, Some(BulkEmailStatus::MessageRejected));
+        assert_eq!(results[1].message_id, None);
+        assert_eq!(
+            results[1].error.as_deref(),
+            Some("Email address is not verified."),
+        );
+
+        let body = request_body.lock().unwrap();
+        assert!(body.contains(r#""TemplateName":"welcome""#));
+        assert!(body.contains(r#""TemplateData":"{\"name\":\"Someone\"}""#));
+        assert!(body.contains(r#""ReplacementTemplateData":"{\"name\":\"First\"}""#));
+        assert!(body.contains(r#""DefaultEmailTags":[{"Name":"app","Value":"gen_mailer"}]"#));
+        assert_eq!(body.matches("ReplacementTemplateData").count(), 1);
+    }
+
+    #[test]
+    fn test_aws_ses_mailer_content_mode() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let mut with_attachment = message.clone();
+        with_attachment.attachments.push(MessageAttachment::new(
+            "a.txt",
+            "text/plain",
+            b"A".as_slice(),
+        ));
+
+        assert!(!mailer(AwsSesContentMode::Auto).use_raw_content(&message));
+        assert!(mailer(AwsSesContentMode::Auto).use_raw_content(&with_attachment));
+        assert!(!mailer(AwsSesContentMode::Simple).use_raw_content(&with_attachment));
+        assert!(mailer(AwsSesContentMode::Raw).use_raw_content(&message));
+
+        let content = AwsSesMailer::build_raw_content(&with_attachment);
+        let data = content.raw().unwrap().data().as_ref();
+        let data = std::str::from_utf8(data).unwrap();
+        assert!(data.starts_with("From: sender@example.com\r\n"));
+        assert!(data.contains("Content-Disposition: attachment; filename=\"a.txt\""));
+    }
+
+    #[test]
+    fn test_aws_ses_mailer_tags_and_headers() {
+        let message = Message::builder()
+            .category("password reset")
+            .metadata("user_id", "42")
+            .metadata("source.app", "web/é")
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .headers("X-Test", "yes")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let mailer = mailer(AwsSesContentMode::Simple);
+        let content = AwsSesMailer::build_content(&message);
+        let input = mailer
+            .build_send_email(&message, content)
+            .unwrap()
+            .as_input()
+            .clone()
+            .build()
+            .unwrap();
+
+        let tags: Vec<_> = input
+            .email_tags()
+            .iter()
+            .map(|t| (t.name(), t.value()))
+            .collect();
+        assert_eq!(
+            tags,
+            [
+                ("category", "password_reset"),
+                ("user_id", "42"),
+                ("source_app", "web__"),
+            ],
+        );
+
+        let headers: Vec<_> = input
+            .content()
+            .and_then(|c| c.simple())
+            .map(|s| s.headers())
+            .unwrap_or_default()
+            .iter()
+            .map(|h| (h.name(), h.value()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ("Message-ID", "<abc123@example.com>"),
+                ("Date", "Thu, 01 Jan 1970 00:00:00 +0000"),
+                ("X-Test", "yes"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_aws_ses_mailer_template() {
+        let template = MessageTemplate::new("welcome").variable("name", "Recipient");
+
+        let content = AwsSesMailer::build_template_content(&template);
+        let template = content.template().unwrap();
+
+        assert_eq!(template.template_name(), Some("welcome"));
+        assert_eq!(template.template_data(), Some(r#"{"name":"Recipient"}"#));
+    }
+
+    #[test]
+    fn test_sanitize_tags() {
+        let tags = sanitize_tags([("user_id-1", "a b"), ("a b.c/d", &"x".repeat(300))]).unwrap();
+        assert_eq!(tags[0], ("user_id-1".to_string(), "a_b".to_string()));
+        assert_eq!(tags[1].0, "a_b_c_d");
+        assert_eq!(tags[1].1.len(), 256);
+
+        // SES rejects the email for empty or repeated names, so they fail early
+        assert!(sanitize_tags([("", "x")]).is_err());
+        assert!(sanitize_tags([("a.b", "x"), ("a/b", "y")]).is_err());
+    }
+
+    #[test]
+    fn test_aws_ses_mailer_bulk_entry() {
+        let destination = AwsSesBulkDestination {
+            to: vec![Address::with_name("Recipient", "recipient@example.com")],
+            variables: vec![("name".into(), TemplateValue::String("Recipient".into()))],
+            ..Default::default()
+        };
+
+        let entry = AwsSesMailer::build_bulk_entry(&destination);
+
+        let to = entry.destination().unwrap().to_addresses();
+        assert_eq!(to, ["\"Recipient\" <recipient@example.com>"]);
+        assert!(entry.destination().unwrap().cc_addresses().is_empty());
+
+        let data = entry
+            .replacement_email_content()
+            .and_then(|c| c.replacement_template())
+            .and_then(|t| t.replacement_template_data());
+        assert_eq!(data, Some(r#"{"name":"Recipient"}"#));
+    }
+}
@@ -0,0 +1,489 @@
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use hmac::Hmac;
+use hmac::Mac;
+use serde_json::json;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+use crate::utils::format_http_date;
+
+const API_VERSION: &str = "2023-03-31";
+
+pub struct AzureEmailMailer {
+    client: reqwest::Client,
+    endpoint: String,
+    access_key: Vec<u8>,
+    poll_interval: Duration,
+    max_wait: Duration,
+}
+
+/// The status of a send operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AzureEmailStatus {
+    NotStarted,
+    Running,
+    Succeeded,
+    Failed(Option<ProviderError>),
+    Canceled,
+}
+
+impl AzureEmailStatus {
+    pub fn is_done(&self) -> bool {
+        return !matches!(
+            self,
+            AzureEmailStatus::NotStarted | AzureEmailStatus::Running
+        );
+    }
+}
+
+impl AzureEmailMailer {
+    /// Uses the resource endpoint and its (base64 encoded) access key.
+    pub fn new(endpoint: impl Into<String>, access_key: &str) -> Result<Self, GenericMailerError> {
+        return Self::with_client(reqwest::Client::new(), endpoint, access_key);
+    }
+
+    pub fn with_client(
+        client: reqwest::Client,
+        endpoint: impl Into<String>,
+        access_key: &str,
+    ) -> Result<Self, GenericMailerError> {
+        let access_key = BASE64_STANDARD
+            .decode(access_key)
+            .map_err(|err| GenericMailerError::UnexpectedError(Box::new(err)))?;
+
+        return Ok(Self {
+            client,
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            access_key,
+            poll_interval: Duration::from_secs(5),
+            max_wait: Duration::from_secs(300),
+        });
+    }
+
+    /// Uses a connection string, e.g. `endpoint=https://...;accesskey=...`
+    pub fn from_connection_string(connection_string: &str) -> Result<Self, GenericMailerError> {
+        let mut endpoint = None;
+        let mut access_key = None;
+
+        for part in connection_string.split(';') {
+            match part.split_once('=') {
+                Some((k, v)) if k.eq_ignore_ascii_case("endpoint") => endpoint = Some(v),
+                Some((k, v)) if k.eq_ignore_ascii_case("accesskey") => access_key = Some(v),
+                _ => {}
+            }
+        }
+
+        let (Some(endpoint), Some(access_key)) = (endpoint, access_key) else {
+            let error = "connection string is missing the endpoint or accesskey";
+            return Err(GenericMailerError::UnexpectedError(error.into()));
+        };
+
+        return Self::new(endpoint, access_key);
+    }
+
+    /// How long to wait between checks in `wait_for_operation`, defaults to 5 seconds.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+
+        return self;
+    }
+
+    /// How long `wait_for_operation` waits before giving up, defaults to 5 minutes.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+
+        return self;
+    }
+
+    // See: https://learn.microsoft.com/en-us/rest/api/communication/email/get-send-result
+    pub async fn operation_status(
+        &self,
+        operation_id: &str,
+    ) -> Result<AzureEmailStatus, GenericMailerError> {
+        let url = format!(
+            "{}/emails/operations/{operation_id}?api-version={API_VERSION}",
+            self.endpoint
+        );
+
+        let response = self.request(reqwest::Method::GET, &url, None).await?;
+        let json = response.json()?;
+
+        let Some(status) = json.get("status").and_then(|s| s.as_str()) else {
+            return Err(response.unexpected());
+        };
+
+        return match status {
+            "NotStarted" => Ok(AzureEmailStatus::NotStarted),
+            "Running" => Ok(AzureEmailStatus::Running),
+            "Succeeded" => Ok(AzureEmailStatus::Succeeded),
+            "Failed" => Ok(AzureEmailStatus::Failed(parse_error(&json))),
+            "Canceled" => Ok(AzureEmailStatus::Canceled),
+            _ => Err(response.unexpected()),
+        };
+    }
+
+    /// Polls the operation until it is done, returning its final status.
+    ///
+    /// Fails if the operation is still not done after the `max_wait`.
+    pub async fn wait_for_operation(
+        &self,
+        operation_id: &str,
+    ) -> Result<AzureEmailStatus, GenericMailerError> {
+        let deadline = Instant::now() + self.max_wait;
+
+        loop {
+            let status = self.operation_status(operation_id).await?;
+            if status.is_done() {
+                return Ok(status);
+            }
+
+            if Instant::now() + self.poll_interval > deadline {
+                let error = format!(
+                    "operation {operation_id} is not done after {:?}",
+                    self.max_wait
+                );
+                return Err(GenericMailerError::UnexpectedError(error.into()));
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<ApiResponse, GenericMailerError> {
+        let url = reqwest::Url::parse(url)
+            .map_err(|err| GenericMailerError::UnexpectedError(Box::new(err)))?;
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let date = format_http_date(SystemTime::now());
+
+        let mut request = self.client.request(method.clone(), url.clone());
+        for (k, v) in self.sign(method.as_str(), &url, &date, body.as_bytes()) {
+            request = request.header(k, v);
+        }
+
+        if !body.is_empty() {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        return ApiResponse::read(request.send().await?, parse_error).await;
+    }
+
+    // See: https://learn.microsoft.com/en-us/azure/communication-services/tutorials/hmac-header-tutorial
+    fn sign(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        date: &str,
+        body: &[u8],
+    ) -> [(&'static str, String); 3] {
+        let content_hash = BASE64_STANDARD.encode(Sha256::digest(body));
+
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            (None, _) => String::new(),
+        };
+
+        let path_and_query = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+
+        let string_to_sign = format!("{method}\n{path_and_query}\n{date};{host};{content_hash}");
+
+        // HMAC accepts keys of any length, so this never fails
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.access_key).unwrap();
+        mac.update(string_to_sign.as_bytes());
+        let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+
+        return [
+            ("x-ms-date", date.to_string()),
+            ("x-ms-content-sha256", content_hash),
+            (
+                "Authorization",
+                format!(
+                    "HMAC-SHA256 SignedHeaders=x-ms-date;host;x-ms-content-sha256&Signature={signature}"
+                ),
+            ),
+        ];
+    }
+}
+
+#[async_trait]
+impl GenericMailer for AzureEmailMailer {
+    // See: https://learn.microsoft.com/en-us/rest/api/communication/email/send
+    //
+    // NOTE: Sending is a long-running operation, the returned ID is the operation ID
+    // which can be passed to `wait_for_operation` to get the final status.
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Azure Email", m)?;
+
+        let url = format!("{}/emails:send?api-version={API_VERSION}", self.endpoint);
+        let request = Self::build_request(m);
+
+        let response = self
+            .request(reqwest::Method::POST, &url, Some(&request))
+            .await?;
+        let json = response.json()?;
+
+        let Some(id) = json.get("id").and_then(|id| id.as_str()) else {
+            return Err(response.unexpected());
+        };
+
+        return Ok(vec![id.to_string()]);
+    }
+}
+
+impl AzureEmailMailer {
+    // NOTE: ACS has no equivalent for the category and metadata,
+    // and the sender name is taken from the configured domain.
+    fn build_request(m: &Message) -> serde_json::Value {
+        let mut content = json!({ "subject": m.subject });
+
+        if let Some(body) = &m.text_body {
+            content["plainText"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            content["html"] = json!(body);
+        }
+
+        let mut recipients = json!({
+            "to": m.to.iter().map(Self::build_address).collect::<serde_json::Value>(),
+        });
+
+        if !m.cc.is_empty() {
+            recipients["cc"] = m.cc.iter().map(Self::build_address).collect();
+        }
+
+        if !m.bcc.is_empty() {
+            recipients["bcc"] = m.bcc.iter().map(Self::build_address).collect();
+        }
+
+        let mut req = json!({
+            "senderAddress": m.from.email,
+            "content": content,
+            "recipients": recipients,
+        });
+
+        if let Some(reply_to) = &m.reply_to {
+            req["replyTo"] = json!([Self::build_address(reply_to)]);
+        }
+
+        if !m.attachments.is_empty() {
+            req["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "name": a.name,
+                        "contentType": a.content_type,
+                        "contentInBase64": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let map =
+                serde_json::Map::from_iter(headers.iter().map(|(k, v)| (k.to_string(), json!(v))));
+            req["headers"] = serde_json::Value::from(map);
+        }
+
+        return req;
+    }
+
+    fn build_address(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "address": addr.email, "displayName": name })
+        } else {
+            json!({ "address": addr.email })
+        };
+    }
+}
+
+// e.g. { "error": { "code": "InvalidSenderUserName", "message": "..." } }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let error = json.get("error")?;
+
+    return Some(ProviderError {
+        code: error.get("code").and_then(|c| c.as_str()).map(String::from),
+        message: error.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    // base64("test-access-key-for-azure-email")
+    const TEST_ACCESS_KEY: &str = "dGVzdC1hY2Nlc3Mta2V5LWZvci1henVyZS1lbWFpbA==";
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .from("sender@example.com")
+            .bcc("bcc@example.com")
+            .headers("X-Test", "yes")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_azure_email_mailer() {
+        let expected = json!({
+            "senderAddress": "sender@example.com",
+            "content": {
+                "subject": "Test Email",
+                "plainText": "This is a test email.",
+            },
+            "attachments": [
+                { "name": "hello.txt", "contentType": "text/plain", "contentInBase64": "SGkh" },
+            ],
+            "recipients": {
+                "to": [{ "address": "recipient@example.com", "displayName": "Recipient" }],
+                "bcc": [{ "address": "bcc@example.com" }],
+            },
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                "X-Test": "yes",
+            },
+        });
+
+        let actual = AzureEmailMailer::build_request(&message());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_azure_email_sign() {
+        let connection_string = format!(
+            "endpoint=https://contoso.communication.azure.com/;accesskey={TEST_ACCESS_KEY}"
+        );
+        let mailer = AzureEmailMailer::from_connection_string(&connection_string).unwrap();
+
+        let url = reqwest::Url::parse(
+            "https://contoso.communication.azure.com/emails:send?api-version=2023-03-31",
+        )
+        .unwrap();
+        let body = br#"{"senderAddress":"sender@example.com"}"#;
+        let actual = mailer.sign("POST", &url, "Sun, 06 Nov 1994 08:49:37 GMT", body);
+
+        let expected = [
+            ("x-ms-date", "Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            (
+                "x-ms-content-sha256",
+                "WiiLNN/Ssi6nNZ6ycr6lNwLOKaAkgP29t5GPqMx5pvA=".to_string(),
+            ),
+            (
+                "Authorization",
+                "HMAC-SHA256 SignedHeaders=x-ms-date;host;x-ms-content-sha256&Signature=Jmw3InimA3+VXpygPgIHve0rlmD3P9j/y0IJbY7nanc=".to_string(),
+            ),
+        ];
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_azure_email_mailer_send() {
+        let server = MockServer::start().await;
+        let operation_id = "f5b4d7b1-7c6a-4c5a-9a52-7b1c1e8f3d2a";
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/emails:send"))
+            .and(matchers::query_param("api-version", API_VERSION))
+            .and(matchers::header_exists("x-ms-date"))
+            .and(matchers::header_exists("x-ms-content-sha256"))
+            .respond_with(ResponseTemplate::new(202).set_body_json(json!({
+                "id": operation_id,
+                "status": "Running",
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path(format!("/emails/operations/{operation_id}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": operation_id,
+                "status": "Running",
+                "error": null,
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path(format!("/emails/operations/{operation_id}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": operation_id,
+                "status": "Succeeded",
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = AzureEmailMailer::new(server.uri(), TEST_ACCESS_KEY)
+            .unwrap()
+            .poll_interval(Duration::from_millis(10));
+
+        let ids = mailer.send(&message()).await.unwrap();
+        assert_eq!(ids, [operation_id]);
+
+        let status = mailer.wait_for_operation(&ids[0]).await.unwrap();
+        assert_eq!(status, AzureEmailStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_azure_email_mailer_wait_timeout() {
+        let server = MockServer::start().await;
+        let operation_id = "f5b4d7b1-7c6a-4c5a-9a52-7b1c1e8f3d2a";
+
+        Mock::given(matchers::method("GET"))
+            .and(matchers::path(format!("/emails/operations/{operation_id}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": operation_id,
+                "status": "Running",
+                "error": null,
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = AzureEmailMailer::new(server.uri(), TEST_ACCESS_KEY)
+            .unwrap()
+            .poll_interval(Duration::from_millis(10))
+            .max_wait(Duration::from_millis(50));
+
+        let err = mailer.wait_for_operation(operation_id).await.unwrap_err();
+
+        assert!(matches!(err, GenericMailerError::UnexpectedError(_)));
+    }
+}
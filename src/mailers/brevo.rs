@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+
+pub struct BrevoMailer {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl BrevoMailer {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key)
+    }
+
+    pub fn with_client(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: "https://api.brevo.com".to_string(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for BrevoMailer {
+    // See: https://developers.brevo.com/reference/sendtransacemail
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        let request = Self::build_request(m)?;
+
+        let response = self
+            .client
+            .post(format!("{}/v3/smtp/email", self.base_url))
+            .header("api-key", &self.api_key)
+            .header("Accept", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+
+        // NOTE: Brevo only answers with `messageIds` (plural) for `messageVersions`, unused here
+        let message_id = response.json().ok().and_then(|json| {
+            return Some(json.get("messageId")?.as_str()?.to_string());
+        });
+
+        return Ok(message_id.into_iter().collect());
+    }
+}
+
+impl BrevoMailer {
+    fn build_request(m: &Message) -> Result<serde_json::Value, GenericMailerError> {
+        let mut req = json!({
+            "sender": Self::build_address(&m.from),
+            "to": m.to.iter().map(Self::build_address).collect::<serde_json::Value>(),
+        });
+
+        if !m.cc.is_empty() {
+            req["cc"] = m.cc.iter().map(Self::build_address).collect();
+        }
+
+        if !m.bcc.is_empty() {
+            req["bcc"] = m.bcc.iter().map(Self::build_address).collect();
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            req["replyTo"] = Self::build_address(reply_to);
+        }
+
+        // NOTE: With a template, the subject is an optional override and the body is ignored
+        if let Some(template) = &m.template {
+            let Ok(template_id) = template.id.parse::<u64>() else {
+                let error = format!("Brevo template IDs are numeric, got: {}", template.id);
+                return Err(GenericMailerError::UnexpectedError(error.into()));
+            };
+
+            req["templateId"] = json!(template_id);
+
+            if !template.variables.is_empty() {
+                req["params"] = template.variables_json();
+            }
+
+            if !m.subject.is_empty() {
+                req["subject"] = json!(m.subject);
+            }
+        } else {
+            req["subject"] = json!(m.subject);
+
+            if let Some(body) = &m.text_body {
+                req["textContent"] = json!(body);
+            }
+
+            if let Some(body) = &m.html_body {
+                req["htmlContent"] = json!(body);
+            }
+        }
+
+        if !m.attachments.is_empty() {
+            req["attachment"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "name": a.name,
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let mut headers = serde_json::Map::from_iter(
+            m.all_headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), json!(v))),
+        );
+
+        if let Some(category) = &m.category {
+            req["tags"] = json!([category]);
+        }
+
+        if !m.metadata.is_empty() {
+            let map = serde_json::Map::from_iter(
+                m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))),
+            );
+
+            // NOTE: Brevo has no metadata, but it echoes back the `X-Mailin-custom` header
+            // in its webhooks, so the metadata is sent as a JSON object string there.
+            let map = serde_json::Value::from(map);
+            headers.insert("X-Mailin-custom".to_string(), json!(map.to_string()));
+        }
+
+        if !headers.is_empty() {
+            req["headers"] = serde_json::Value::from(headers);
+        }
+
+        return Ok(req);
+    }
+
+    fn build_address(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "email": addr.email, "name": name })
+        } else {
+            json!({ "email": addr.email })
+        };
+    }
+}
+
+// e.g. { "code": "invalid_parameter", "message": "..." }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    return Some(ProviderError {
+        code: json.get("code").and_then(|c| c.as_str()).map(String::from),
+        message: json.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::MessageTemplate;
+    use crate::mailers::test_message;
+
+    #[test]
+    fn test_brevo_mailer() {
+        let message = test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .cc(Address::new("cc@example.com"))
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "sender": { "name": "Sender", "email": "sender@example.com" },
+            "to": [{ "name": "Recipient", "email": "recipient@example.com" }],
+            "cc": [{ "email": "cc@example.com" }],
+            "subject": "Test Email",
+            "textContent": "This is a test email.",
+            "attachment": [{ "name": "hello.txt", "content": "SGkh" }],
+            "tags": ["welcome"],
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                "X-Mailin-custom": "{\"user_id\":\"42\"}",
+            },
+        });
+
+        let actual = BrevoMailer::build_request(&message).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_brevo_mailer_template() {
+        let message = Message::builder()
+            .from(Address::new("sender@example.com"))
+            .to(Address::new("recipient@example.com"))
+            .template(MessageTemplate::new("7").variable("name", "Recipient"))
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "sender": { "email": "sender@example.com" },
+            "to": [{ "email": "recipient@example.com" }],
+            "templateId": 7,
+            "params": { "name": "Recipient" },
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+            },
+        });
+
+        let actual = BrevoMailer::build_request(&message).unwrap();
+
+        assert_eq!(actual, expected);
+
+        let message = Message::builder()
+            .from(Address::new("sender@example.com"))
+            .to(Address::new("recipient@example.com"))
+            .template(MessageTemplate::new("welcome"))
+            .build()
+            .unwrap();
+
+        assert!(BrevoMailer::build_request(&message).is_err());
+    }
+}
@@ -14,7 +14,7 @@ impl GenericMailer for ConsoleMailer {
     async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
         Self::send(io::stdout(), m)?;
 
-        return Ok(Vec::new());
+        return Ok(m.message_id.iter().map(|id| id.to_string()).collect());
     }
 }
 
@@ -43,6 +43,11 @@ impl ConsoleMailer {
         }
 
         writeln!(w, "Subject: {}", m.subject)?;
+
+        if let Some(template) = &m.template {
+            writeln!(w, "Template: {}", template.id)?;
+        }
+
         writeln!(w)?;
 
         if let Some(body) = &m.text_body {
@@ -0,0 +1,325 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct MailerSendMailer {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl MailerSendMailer {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key)
+    }
+
+    pub fn with_client(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: "https://api.mailersend.com".to_string(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+
+    /// Sends the message with per-recipient personalization variables, keyed by the email address.
+    pub async fn send_with_personalization(
+        &self,
+        m: &Message<'_>,
+        personalization: &[(&str, serde_json::Value)],
+    ) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("MailerSend", m)?;
+
+        let request = Self::build_request(m, personalization);
+
+        return self.post(&request).await;
+    }
+
+    async fn post(&self, request: &serde_json::Value) -> Result<Vec<String>, GenericMailerError> {
+        let response = self
+            .client
+            .post(format!("{}/v1/email", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(request)
+            .send()
+            .await?;
+
+        // NOTE: MailerSend answers 202 with an empty body, the ID is only in this header
+        let message_id = response
+            .headers()
+            .get("X-Message-Id")
+            .and_then(|id| id.to_str().ok())
+            .map(String::from);
+
+        ApiResponse::read(response, Self::parse_error).await?;
+
+        return Ok(message_id.into_iter().collect());
+    }
+}
+
+#[async_trait]
+impl GenericMailer for MailerSendMailer {
+    // See: https://developers.mailersend.com/api/v1/email.html#send-an-email
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("MailerSend", m)?;
+
+        let request = Self::build_request(m, &[]);
+
+        return self.post(&request).await;
+    }
+}
+
+impl MailerSendMailer {
+    // NOTE: MailerSend has no equivalent for the metadata
+    fn build_request(
+        m: &Message,
+        personalization: &[(&str, serde_json::Value)],
+    ) -> serde_json::Value {
+        let mut req = json!({
+            "from": Self::build_address(&m.from),
+            "to": m.to.iter().map(Self::build_address).collect::<serde_json::Value>(),
+            "subject": m.subject,
+        });
+
+        if !m.cc.is_empty() {
+            req["cc"] = m.cc.iter().map(Self::build_address).collect();
+        }
+
+        if !m.bcc.is_empty() {
+            req["bcc"] = m.bcc.iter().map(Self::build_address).collect();
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            req["reply_to"] = Self::build_address(reply_to);
+        }
+
+        if let Some(body) = &m.text_body {
+            req["text"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            req["html"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            req["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "filename": a.name,
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                        "disposition": "attachment",
+                    })
+                })
+                .collect();
+        }
+
+        if let Some(category) = &m.category {
+            req["tags"] = json!([category]);
+        }
+
+        if !personalization.is_empty() {
+            req["personalization"] = personalization
+                .iter()
+                .map(|(email, data)| json!({ "email": email, "data": data }))
+                .collect();
+        }
+
+        // NOTE: Custom headers are only available on some of the paid plans
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            req["headers"] = headers
+                .iter()
+                .map(|(k, v)| json!({ "name": k, "value": v }))
+                .collect();
+        }
+
+        return req;
+    }
+
+    fn build_address(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "email": addr.email, "name": name })
+        } else {
+            json!({ "email": addr.email })
+        };
+    }
+
+    // e.g. { "message": "...", "errors": { "from.email": ["The from.email must be verified."] } }
+    fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+        let message = json.get("message")?.as_str()?;
+
+        let details = json
+            .get("errors")
+            .and_then(|e| e.as_object())
+            .into_iter()
+            .flatten()
+            .flat_map(|(field, messages)| {
+                let messages = messages.as_array().into_iter().flatten();
+                return messages
+                    .filter_map(|m| m.as_str())
+                    .map(|m| (field.to_string(), m.to_string()));
+            })
+            .collect();
+
+        return Some(ProviderError {
+            code: None,
+            message: message.to_string(),
+            details,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("welcome")
+            .bcc(Address::new("bcc@example.com"))
+            .subject("Hi {{ name }}")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mailersend_mailer() {
+        let expected = json!({
+            "from": { "name": "Sender", "email": "sender@example.com" },
+            "to": [{ "name": "Recipient", "email": "recipient@example.com" }],
+            "bcc": [{ "email": "bcc@example.com" }],
+            "subject": "Hi {{ name }}",
+            "text": "This is a test email.",
+            "attachments": [
+                { "filename": "hello.txt", "content": "SGkh", "disposition": "attachment" },
+            ],
+            "tags": ["welcome"],
+            "headers": [
+                { "name": "Message-ID", "value": "<abc123@example.com>" },
+                { "name": "Date", "value": "Thu, 01 Jan 1970 00:00:00 +0000" },
+            ],
+            "personalization": [
+                { "email": "recipient@example.com", "data": { "name": "Recipient" } },
+            ],
+        });
+
+        let personalization = [("recipient@example.com", json!({ "name": "Recipient" }))];
+        let actual = MailerSendMailer::build_request(&message(), &personalization);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_mailersend_mailer_without_headers() {
+        // A free plan rejects custom headers, so none are sent unless set
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let actual = MailerSendMailer::build_request(&message, &[]);
+
+        assert_eq!(actual.get("headers"), None);
+    }
+
+    #[tokio::test]
+    async fn test_mailersend_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v1/email"))
+            .and(matchers::header("Authorization", "Bearer test-key"))
+            .respond_with(
+                ResponseTemplate::new(202)
+                    .insert_header("X-Message-Id", "5e42957d51f1d94a1070a733"),
+            )
+            .mount(&server)
+            .await;
+
+        let mailer = MailerSendMailer::new("test-key").base_url(server.uri());
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert_eq!(ids, ["5e42957d51f1d94a1070a733"]);
+    }
+
+    #[tokio::test]
+    async fn test_mailersend_mailer_send_without_id() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v1/email"))
+            .respond_with(ResponseTemplate::new(202))
+            .mount(&server)
+            .await;
+
+        let mailer = MailerSendMailer::new("test-key").base_url(server.uri());
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert!(ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mailersend_mailer_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v1/email"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "The from.email must be verified. (and 1 more error)",
+                "errors": {
+                    "from.email": ["The from.email must be verified."],
+                    "to.0.email": ["The to.0.email must be a valid email address."],
+                },
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = MailerSendMailer::new("test-key").base_url(server.uri());
+        let err = mailer.send(&message()).await.unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 422);
+        assert_eq!(error.code, None);
+        assert_eq!(
+            error.details,
+            [
+                (
+                    "from.email".to_string(),
+                    "The from.email must be verified.".to_string()
+                ),
+                (
+                    "to.0.email".to_string(),
+                    "The to.0.email must be a valid email address.".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use async_trait::async_trait;
+use reqwest::multipart::Form;
+use reqwest::multipart::Part;
+
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct MailgunMailer {
+    client: reqwest::Client,
+    api_key: String,
+    domain: String,
+    base_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailgunRegion {
+    Us,
+    Eu,
+}
+
+impl MailgunRegion {
+    fn base_url(self) -> &'static str {
+        return match self {
+            MailgunRegion::Us => "https://api.mailgun.net",
+            MailgunRegion::Eu => "https://api.eu.mailgun.net",
+        };
+    }
+}
+
+impl MailgunMailer {
+    pub fn new(api_key: impl Into<String>, domain: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key, domain)
+    }
+
+    pub fn with_client(
+        client: reqwest::Client,
+        api_key: impl Into<String>,
+        domain: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            domain: domain.into(),
+            base_url: MailgunRegion::Us.base_url().to_string(),
+        }
+    }
+
+    pub fn region(mut self, region: MailgunRegion) -> Self {
+        self.base_url = region.base_url().to_string();
+
+        return self;
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for MailgunMailer {
+    // See: https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Messages/
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Mailgun", m)?;
+
+        let mut form = Self::build_fields(m)
+            .into_iter()
+            .fold(Form::new(), |form, (k, v)| form.text(k, v));
+
+        for attachment in &m.attachments {
+            let part = Part::bytes(attachment.bytes.to_vec())
+                .file_name(attachment.name.to_string())
+                .mime_str(&attachment.content_type)?;
+            form = form.part("attachment", part);
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/v3/{}/messages", self.base_url, self.domain))
+            .basic_auth("api", Some(&self.api_key))
+            .multipart(form)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+        let json = response.json()?;
+
+        let Some(id) = json.get("id").and_then(|id| id.as_str()) else {
+            return Err(response.unexpected());
+        };
+
+        return Ok(vec![id.to_string()]);
+    }
+}
+
+impl MailgunMailer {
+    fn build_fields(m: &Message) -> Vec<(String, String)> {
+        let mut fields = vec![("from".to_string(), m.from.to_string())];
+
+        for addr in &m.to {
+            fields.push(("to".to_string(), addr.to_string()));
+        }
+
+        for addr in &m.cc {
+            fields.push(("cc".to_string(), addr.to_string()));
+        }
+
+        for addr in &m.bcc {
+            fields.push(("bcc".to_string(), addr.to_string()));
+        }
+
+        fields.push(("subject".to_string(), m.subject.to_string()));
+
+        if let Some(body) = &m.text_body {
+            fields.push(("text".to_string(), body.to_string()));
+        }
+
+        if let Some(body) = &m.html_body {
+            fields.push(("html".to_string(), body.to_string()));
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            fields.push(("h:Reply-To".to_string(), reply_to.to_string()));
+        }
+
+        for (k, v) in m.all_headers() {
+            fields.push((format!("h:{k}"), v.to_string()));
+        }
+
+        if let Some(category) = &m.category {
+            fields.push(("o:tag".to_string(), category.to_string()));
+        }
+
+        for (k, v) in &m.metadata {
+            fields.push((format!("v:{k}"), v.to_string()));
+        }
+
+        return fields;
+    }
+}
+
+// e.g. { "message": "'from' parameter is missing" }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    return Some(ProviderError {
+        code: None,
+        message: json.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::Address;
+    use crate::mailers::test_message;
+
+    #[test]
+    fn test_mailgun_mailer() {
+        let message = Message::builder()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .from(Address::with_name("Sender", "sender@example.com"))
+            .reply_to("reply@example.com")
+            .to(Address::with_name("Recipient", "recipient@example.com"))
+            .cc(Address::new("cc@example.com"))
+            .headers("X-Test", "yes")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let expected = [
+            ("from", "\"Sender\" <sender@example.com>"),
+            ("to", "\"Recipient\" <recipient@example.com>"),
+            ("cc", "cc@example.com"),
+            ("subject", "Test Email"),
+            ("text", "This is a test email."),
+            ("h:Reply-To", "reply@example.com"),
+            ("h:Message-ID", "<abc123@example.com>"),
+            ("h:Date", "Thu, 01 Jan 1970 00:00:00 +0000"),
+            ("h:X-Test", "yes"),
+            ("o:tag", "welcome"),
+            ("v:user_id", "42"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .to_vec();
+
+        let actual = MailgunMailer::build_fields(&message);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_mailgun_mailer_send_attachment() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v3/example.com/messages"))
+            .and(matchers::body_string_contains(
+                r#"name="attachment"; filename="hello.txt""#,
+            ))
+            .and(matchers::body_string_contains("Content-Type: text/plain"))
+            .and(matchers::body_string_contains("Hi!"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "<abc123@example.com>",
+                "message": "Queued. Thank you.",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let message = test_message().build().unwrap();
+
+        let mailer = MailgunMailer::new("key", "example.com").base_url(server.uri());
+        let ids = mailer.send(&message).await.unwrap();
+
+        assert_eq!(ids, ["<abc123@example.com>"]);
+    }
+}
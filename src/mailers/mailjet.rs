@@ -0,0 +1,365 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct MailjetMailer {
+    client: reqwest::Client,
+    api_key: String,
+    api_secret: String,
+    base_url: String,
+}
+
+impl MailjetMailer {
+    pub fn new(api_key: impl Into<String>, api_secret: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key, api_secret)
+    }
+
+    pub fn with_client(
+        client: reqwest::Client,
+        api_key: impl Into<String>,
+        api_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            api_secret: api_secret.into(),
+            base_url: "https://api.mailjet.com".to_string(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for MailjetMailer {
+    // See: https://dev.mailjet.com/email/reference/send-emails/#v3_1_post_send
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Mailjet", m)?;
+
+        let request = json!({ "Messages": [Self::build_message(m)] });
+
+        let response = self
+            .client
+            .post(format!("{}/v3.1/send", self.base_url))
+            .basic_auth(&self.api_key, Some(&self.api_secret))
+            .json(&request)
+            .send()
+            .await?;
+
+        // NOTE: Mailjet reports errors per message, even if only one is sent
+        let response = ApiResponse::read(response, |json| {
+            let error = json.pointer("/Messages/0/Errors/0").unwrap_or(json);
+            return Self::parse_error(error);
+        })
+        .await?;
+        let json = response.json()?;
+
+        let Some(result) = json.pointer("/Messages/0") else {
+            return Err(response.unexpected());
+        };
+
+        if result.get("Status").and_then(|s| s.as_str()) != Some("success") {
+            let error = result.pointer("/Errors/0").and_then(Self::parse_error);
+            return Err(response.error(error));
+        }
+
+        return Ok(Self::parse_message_ids(result));
+    }
+}
+
+impl MailjetMailer {
+    fn build_message(m: &Message) -> serde_json::Value {
+        let mut message = json!({
+            "From": Self::build_address(&m.from),
+            "To": m.to.iter().map(Self::build_address).collect::<serde_json::Value>(),
+            "Subject": m.subject,
+        });
+
+        if !m.cc.is_empty() {
+            message["Cc"] = m.cc.iter().map(Self::build_address).collect();
+        }
+
+        if !m.bcc.is_empty() {
+            message["Bcc"] = m.bcc.iter().map(Self::build_address).collect();
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            message["ReplyTo"] = Self::build_address(reply_to);
+        }
+
+        if let Some(body) = &m.text_body {
+            message["TextPart"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            message["HTMLPart"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            message["Attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "ContentType": a.content_type,
+                        "Filename": a.name,
+                        "Base64Content": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let map =
+                serde_json::Map::from_iter(headers.iter().map(|(k, v)| (k.to_string(), json!(v))));
+            message["Headers"] = serde_json::Value::from(map);
+        }
+
+        if let Some(category) = &m.category {
+            message["CustomCampaign"] = json!(category);
+        }
+
+        // NOTE: The payload is an opaque string, so the metadata is sent as a JSON object string
+        if !m.metadata.is_empty() {
+            let map = serde_json::Map::from_iter(
+                m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))),
+            );
+            message["EventPayload"] = json!(serde_json::Value::from(map).to_string());
+        }
+
+        return message;
+    }
+
+    fn build_address(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "Email": addr.email, "Name": name })
+        } else {
+            json!({ "Email": addr.email })
+        };
+    }
+
+    // Each recipient gets its own message ID
+    fn parse_message_ids(result: &serde_json::Value) -> Vec<String> {
+        return ["To", "Cc", "Bcc"]
+            .iter()
+            .flat_map(|key| result.get(key).and_then(|r| r.as_array()))
+            .flatten()
+            .flat_map(|r| r.get("MessageID"))
+            .map(|id| match id.as_str() {
+                Some(id) => id.to_string(),
+                None => id.to_string(),
+            })
+            .collect();
+    }
+
+    // e.g. { "ErrorCode": "mj-0013", "StatusCode": 400, "ErrorMessage": "..." }
+    fn parse_error(error: &serde_json::Value) -> Option<ProviderError> {
+        return Some(ProviderError {
+            code: error
+                .get("ErrorCode")
+                .and_then(|c| c.as_str())
+                .map(String::from),
+            message: error.get("ErrorMessage")?.as_str()?.to_string(),
+            details: Vec::new(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    #[test]
+    fn test_mailjet_mailer() {
+        let message = test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .cc(Address::new("cc@example.com"))
+            .headers("X-Test", "yes")
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "From": { "Name": "Sender", "Email": "sender@example.com" },
+            "To": [{ "Name": "Recipient", "Email": "recipient@example.com" }],
+            "Cc": [{ "Email": "cc@example.com" }],
+            "Subject": "Test Email",
+            "TextPart": "This is a test email.",
+            "Attachments": [
+                { "ContentType": "text/plain", "Filename": "hello.txt", "Base64Content": "SGkh" },
+            ],
+            "Headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                "X-Test": "yes",
+            },
+            "CustomCampaign": "welcome",
+            "EventPayload": "{\"user_id\":\"42\"}",
+        });
+
+        let actual = MailjetMailer::build_message(&message);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_mailjet_parse_message_ids() {
+        let result = json!({
+            "Status": "success",
+            "To": [{ "Email": "recipient@example.com", "MessageID": 576460752303423500_u64 }],
+            "Cc": [{ "Email": "cc@example.com", "MessageID": 576460752303423501_u64 }],
+            "Bcc": [],
+        });
+
+        let actual = MailjetMailer::parse_message_ids(&result);
+
+        assert_eq!(actual, ["576460752303423500", "576460752303423501"]);
+    }
+
+    #[tokio::test]
+    async fn test_mailjet_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v3.1/send"))
+            // base64("key:secret")
+            .and(matchers::header("Authorization", "Basic a2V5OnNlY3JldA=="))
+            .and(matchers::body_partial_json(json!({
+                "Messages": [{ "Subject": "Test Email" }],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "Messages": [{
+                    "Status": "success",
+                    "To": [{ "Email": "recipient@example.com", "MessageID": 576460752303423500_u64 }],
+                }],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mailer = MailjetMailer::new("key", "secret").base_url(server.uri());
+        let ids = mailer.send(&test_message().build().unwrap()).await.unwrap();
+
+        assert_eq!(ids, ["576460752303423500"]);
+    }
+
+    #[tokio::test]
+    async fn test_mailjet_mailer_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v3.1/send"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "Messages": [{
+                    "Status": "error",
+                    "Errors": [{
+                        "ErrorCode": "mj-0013",
+                        "StatusCode": 400,
+                        "ErrorMessage": "\"recipient\" is an invalid email address.",
+                    }],
+                }],
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = MailjetMailer::new("key", "secret").base_url(server.uri());
+        let err = mailer
+            .send(&test_message().build().unwrap())
+            .await
+            .unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 400);
+        assert_eq!(error.code.as_deref(), Some("mj-0013"));
+        assert_eq!(error.message, "\"recipient\" is an invalid email address.");
+    }
+
+    #[tokio::test]
+    async fn test_mailjet_mailer_message_error() {
+        let server = MockServer::start().await;
+
+        // Mailjet can answer 200 and still report the message itself as failed
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v3.1/send"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "Messages": [{
+                    "Status": "error",
+                    "Errors": [{
+                        "ErrorCode": "send-0008",
+                        "StatusCode": 403,
+                        "ErrorMessage": "\"sender@example.com\" is not an authorized sender.",
+                    }],
+                }],
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = MailjetMailer::new("key", "secret").base_url(server.uri());
+        let err = mailer
+            .send(&test_message().build().unwrap())
+            .await
+            .unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 200);
+        assert_eq!(error.code.as_deref(), Some("send-0008"));
+        assert_eq!(
+            error.message,
+            "\"sender@example.com\" is not an authorized sender."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mailjet_mailer_auth_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v3.1/send"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "ErrorIdentifier": "ad6bfc4f-1b0a-4d49-8e83-3a8c0e1b1f3c",
+                "StatusCode": 401,
+                "ErrorMessage": "API key authentication/authorization failure.",
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = MailjetMailer::new("key", "secret").base_url(server.uri());
+        let err = mailer
+            .send(&test_message().build().unwrap())
+            .await
+            .unwrap_err();
+
+        // Errors outside of a message have no error code
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 401);
+        assert_eq!(error.code, None);
+        assert!(error.message.starts_with("API key authentication"));
+    }
+}
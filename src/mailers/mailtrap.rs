@@ -1,10 +1,14 @@
 use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
 use serde_json::json;
 
 use crate::Address;
 use crate::GenericMailer;
 use crate::GenericMailerError;
 use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
 
 pub struct MailtrapMailer {
     client: reqwest::Client,
@@ -40,31 +44,20 @@ impl GenericMailer for MailtrapMailer {
             .bearer_auth(&self.api_token)
             .json(&request)
             .send()
-            .await
-            .unwrap();
-
-        if !response.status().is_success() {
-            let status_code = response.status().as_u16();
-            let body = response.text().await?;
+            .await?;
 
-            return Err(GenericMailerError::UnexpectedResponse(status_code, body));
-        }
-
-        let status_code = response.status().as_u16();
-        let text = response.text().await?;
-        let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) else {
-            return Err(GenericMailerError::UnexpectedResponse(status_code, text));
-        };
+        let response = ApiResponse::read(response, parse_error).await?;
+        let json = response.json()?;
 
         let Some(true) = json.as_object().and_then(|o| o.get("success")?.as_bool()) else {
-            return Err(GenericMailerError::UnexpectedResponse(status_code, text));
+            return Err(response.unexpected());
         };
 
         let Some(json_message_ids) = json
             .as_object()
             .and_then(|o| o.get("message_ids")?.as_array())
         else {
-            return Err(GenericMailerError::UnexpectedResponse(status_code, text));
+            return Err(response.unexpected());
         };
 
         let ids = json_message_ids
@@ -82,7 +75,6 @@ impl MailtrapMailer {
         let mut req = json!({
             "from": Self::build_address(&m.from),
             "to": m.to.iter().map(Self::build_address).collect::<serde_json::Value>(),
-            "subject": &m.subject,
         });
 
         if !m.cc.is_empty() {
@@ -97,23 +89,47 @@ impl MailtrapMailer {
             req["reply_to"] = Self::build_address(reply_to);
         }
 
-        if let Some(body) = &m.text_body {
-            req["text"] = json!(body);
-        }
+        // NOTE: Mailtrap rejects the subject, body and category when using a template,
+        // since those are all part of the template.
+        if let Some(template) = &m.template {
+            req["template_uuid"] = json!(template.id);
+            req["template_variables"] = template.variables_json();
+        } else {
+            req["subject"] = json!(m.subject);
+
+            if let Some(body) = &m.text_body {
+                req["text"] = json!(body);
+            }
+
+            if let Some(body) = &m.html_body {
+                req["html"] = json!(body);
+            }
 
-        if let Some(body) = &m.html_body {
-            req["html"] = json!(body);
+            if let Some(category) = &m.category {
+                req["category"] = json!(category);
+            }
         }
 
-        if !m.headers.is_empty() {
-            let map = serde_json::Map::from_iter(
-                m.headers.iter().map(|(k, v)| (k.to_string(), json!(v))),
-            );
-            req["headers"] = serde_json::Value::from(map);
+        if !m.attachments.is_empty() {
+            req["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                        "type": a.content_type,
+                        "filename": a.name,
+                    })
+                })
+                .collect();
         }
 
-        if let Some(category) = &m.category {
-            req["category"] = json!(category);
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let map =
+                serde_json::Map::from_iter(headers.iter().map(|(k, v)| (k.to_string(), json!(v))));
+            req["headers"] = serde_json::Value::from(map);
         }
 
         if !m.metadata.is_empty() {
@@ -135,18 +151,33 @@ impl MailtrapMailer {
     }
 }
 
+// e.g. { "success": false, "errors": ["'from' is invalid", "'subject' is required"] }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let errors = json.get("errors")?.as_array()?;
+    let errors: Vec<_> = errors.iter().filter_map(|e| e.as_str()).collect();
+    if errors.is_empty() {
+        return None;
+    }
+
+    return Some(ProviderError {
+        code: None,
+        message: errors.join(", "),
+        details: Vec::new(),
+    });
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::SystemTime;
+
     use super::*;
+    use crate::MessageTemplate;
+    use crate::mailers::test_message;
 
     #[test]
     fn test_mailtrap_mailer() {
-        let message = Message::builder()
-            .from(Address::with_name("Sender", "sender@example.com"))
-            .to(Address::with_name("Recipient", "recipient@example.com"))
+        let message = test_message()
             .cc(Address::new("cc@example.com"))
-            .subject("Test Email")
-            .text_body("This is a test email.")
             .build()
             .unwrap();
 
@@ -156,6 +187,43 @@ mod tests {
             "cc": [{ "email": "cc@example.com" }],
             "subject": "Test Email",
             "text": "This is a test email.",
+            "attachments": [{ "content": "SGkh", "type": "text/plain", "filename": "hello.txt" }],
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+            },
+        });
+
+        let actual = MailtrapMailer::build_request(&message);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_mailtrap_mailer_template() {
+        let message = Message::builder()
+            .category("welcome")
+            .from(Address::new("sender@example.com"))
+            .to(Address::new("recipient@example.com"))
+            .template(
+                MessageTemplate::new("e2bc2d3a-8d2c-4a4b-9f5c-1a6f1c0c9a7d")
+                    .variable("name", "Recipient")
+                    .variable("count", 3),
+            )
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "from": { "email": "sender@example.com" },
+            "to": [{ "email": "recipient@example.com" }],
+            "template_uuid": "e2bc2d3a-8d2c-4a4b-9f5c-1a6f1c0c9a7d",
+            "template_variables": { "name": "Recipient", "count": 3 },
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+            },
         });
 
         let actual = MailtrapMailer::build_request(&message);
@@ -0,0 +1,420 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct MandrillMailer {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+/// The result of sending to a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MandrillRecipientResult {
+    pub email: String,
+    pub status: MandrillStatus,
+    pub reject_reason: Option<String>,
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MandrillStatus {
+    Sent,
+    Queued,
+    Scheduled,
+    Rejected,
+    Invalid,
+}
+
+impl MandrillStatus {
+    fn parse(s: &str) -> Option<Self> {
+        return match s {
+            "sent" => Some(MandrillStatus::Sent),
+            "queued" => Some(MandrillStatus::Queued),
+            "scheduled" => Some(MandrillStatus::Scheduled),
+            "rejected" => Some(MandrillStatus::Rejected),
+            "invalid" => Some(MandrillStatus::Invalid),
+            _ => None,
+        };
+    }
+
+    pub fn is_rejected(self) -> bool {
+        return matches!(self, MandrillStatus::Rejected | MandrillStatus::Invalid);
+    }
+
+    /// Accepted but not sent yet, so it may still be rejected later on.
+    pub fn is_pending(self) -> bool {
+        return matches!(self, MandrillStatus::Queued | MandrillStatus::Scheduled);
+    }
+}
+
+impl MandrillMailer {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key)
+    }
+
+    pub fn with_client(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: "https://mandrillapp.com/api/1.0".to_string(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+
+    /// Sends the message, returning the result for each recipient as reported by Mandrill.
+    ///
+    /// Unlike `send`, this does not fail when some of the recipients were rejected, and tells
+    /// apart the recipients it was sent to from the ones it was only queued or scheduled for.
+    pub async fn send_detailed(
+        &self,
+        m: &Message<'_>,
+    ) -> Result<Vec<MandrillRecipientResult>, GenericMailerError> {
+        reject_template("Mandrill", m)?;
+
+        let request = json!({
+            "key": self.api_key,
+            "message": Self::build_message(m),
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/messages/send", self.base_url))
+            .json(&request)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+        let json = response.json()?;
+
+        let Some(results) = Self::parse_results(&json) else {
+            return Err(response.unexpected());
+        };
+
+        return Ok(results);
+    }
+}
+
+#[async_trait]
+impl GenericMailer for MandrillMailer {
+    // See: https://mailchimp.com/developer/transactional/api/messages/send-new-message/
+    //
+    // NOTE: Mandrill responds with a 200 even if some of the recipients were rejected,
+    // so this fails if any of them were. Only the IDs of the recipients it was sent to are
+    // returned, the queued and scheduled ones may still be rejected (see `send_detailed`).
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        let results = self.send_detailed(m).await?;
+
+        if results.is_empty() {
+            let error = "Mandrill returned no results for the recipients";
+            return Err(GenericMailerError::UnexpectedError(error.into()));
+        }
+
+        let rejected: Vec<_> = results.iter().filter(|r| r.status.is_rejected()).collect();
+        if !rejected.is_empty() {
+            let error = ProviderError {
+                code: Some("rejected".to_string()),
+                message: format!(
+                    "{} of {} recipients were rejected",
+                    rejected.len(),
+                    results.len()
+                ),
+                details: rejected
+                    .iter()
+                    .map(|r| {
+                        let reason = r.reject_reason.as_deref().unwrap_or("invalid");
+                        return (r.email.clone(), reason.to_string());
+                    })
+                    .collect(),
+            };
+
+            return Err(GenericMailerError::ProviderError(200, error));
+        }
+
+        let sent = results
+            .into_iter()
+            .filter(|r| r.status == MandrillStatus::Sent);
+
+        return Ok(sent.filter_map(|r| r.id).collect());
+    }
+}
+
+impl MandrillMailer {
+    fn build_message(m: &Message) -> serde_json::Value {
+        let recipients = (m.to.iter().map(|a| Self::build_recipient(a, "to")))
+            .chain(m.cc.iter().map(|a| Self::build_recipient(a, "cc")))
+            .chain(m.bcc.iter().map(|a| Self::build_recipient(a, "bcc")));
+
+        let mut message = json!({
+            "from_email": m.from.email,
+            "to": recipients.collect::<serde_json::Value>(),
+            "subject": m.subject,
+        });
+
+        if let Some(name) = &m.from.name {
+            message["from_name"] = json!(name);
+        }
+
+        if let Some(body) = &m.text_body {
+            message["text"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            message["html"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            message["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "type": a.content_type,
+                        "name": a.name,
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let mut headers = serde_json::Map::from_iter(
+            m.all_headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), json!(v))),
+        );
+
+        if let Some(reply_to) = &m.reply_to {
+            headers.insert("Reply-To".to_string(), json!(reply_to.to_string()));
+        }
+
+        if !headers.is_empty() {
+            message["headers"] = serde_json::Value::from(headers);
+        }
+
+        // Without this, every recipient would see only themselves in the To header
+        if m.to.len() + m.cc.len() > 1 {
+            message["preserve_recipients"] = json!(true);
+        }
+
+        if let Some(category) = &m.category {
+            message["tags"] = json!([category]);
+        }
+
+        if !m.metadata.is_empty() {
+            let map = serde_json::Map::from_iter(
+                m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))),
+            );
+            message["metadata"] = serde_json::Value::from(map);
+        }
+
+        return message;
+    }
+
+    fn build_recipient(addr: &Address, kind: &str) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "email": addr.email, "name": name, "type": kind })
+        } else {
+            json!({ "email": addr.email, "type": kind })
+        };
+    }
+
+    // e.g. [{ "email": "...", "status": "rejected", "reject_reason": "hard-bounce", "_id": "..." }]
+    fn parse_results(json: &serde_json::Value) -> Option<Vec<MandrillRecipientResult>> {
+        return json
+            .as_array()?
+            .iter()
+            .map(|r| {
+                return Some(MandrillRecipientResult {
+                    email: r.get("email")?.as_str()?.to_string(),
+                    status: MandrillStatus::parse(r.get("status")?.as_str()?)?,
+                    reject_reason: r
+                        .get("reject_reason")
+                        .and_then(|r| r.as_str())
+                        .map(String::from),
+                    id: r.get("_id").and_then(|id| id.as_str()).map(String::from),
+                });
+            })
+            .collect();
+    }
+}
+
+// e.g. { "status": "error", "code": -1, "name": "Invalid_Key", "message": "..." }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    return Some(ProviderError {
+        code: json.get("name").and_then(|n| n.as_str()).map(String::from),
+        message: json.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .cc(Address::new("cc@example.com"))
+            .reply_to(Address::new("reply@example.com"))
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mandrill_mailer() {
+        let expected = json!({
+            "from_email": "sender@example.com",
+            "from_name": "Sender",
+            "to": [
+                { "email": "recipient@example.com", "name": "Recipient", "type": "to" },
+                { "email": "cc@example.com", "type": "cc" },
+            ],
+            "subject": "Test Email",
+            "text": "This is a test email.",
+            "attachments": [{ "type": "text/plain", "name": "hello.txt", "content": "SGkh" }],
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                "Reply-To": "reply@example.com",
+            },
+            "preserve_recipients": true,
+            "tags": ["welcome"],
+            "metadata": { "user_id": "42" },
+        });
+
+        let actual = MandrillMailer::build_message(&message());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_mandrill_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/messages/send"))
+            .and(matchers::body_partial_json(json!({ "key": "test-key" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "email": "recipient@example.com", "status": "sent", "_id": "abc123" },
+                { "email": "cc@example.com", "status": "queued", "_id": "def456" },
+            ])))
+            .mount(&server)
+            .await;
+
+        let mailer = MandrillMailer::new("test-key").base_url(server.uri());
+        // The queued recipient may still be rejected, so only the sent one is returned
+        let ids = mailer.send(&message()).await.unwrap();
+        assert_eq!(ids, ["abc123"]);
+
+        let results = mailer.send_detailed(&message()).await.unwrap();
+        assert!(!results[0].status.is_pending());
+        assert!(results[1].status.is_pending());
+    }
+
+    #[tokio::test]
+    async fn test_mandrill_mailer_rejected() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/messages/send"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "email": "recipient@example.com", "status": "sent", "_id": "abc123" },
+                {
+                    "email": "cc@example.com",
+                    "status": "rejected",
+                    "reject_reason": "hard-bounce",
+                    "_id": "def456",
+                },
+            ])))
+            .mount(&server)
+            .await;
+
+        let mailer = MandrillMailer::new("test-key").base_url(server.uri());
+
+        let results = mailer.send_detailed(&message()).await.unwrap();
+        assert_eq!(results[0].status, MandrillStatus::Sent);
+        assert_eq!(results[1].status, MandrillStatus::Rejected);
+        assert_eq!(results[1].reject_reason.as_deref(), Some("hard-bounce"));
+
+        let err = mailer.send(&message()).await.unwrap_err();
+        let GenericMailerError::ProviderError(200, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(error.message, "1 of 2 recipients were rejected");
+        assert_eq!(
+            error.details,
+            [("cc@example.com".to_string(), "hard-bounce".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mandrill_mailer_all_rejected() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/messages/send"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "email": "recipient@example.com", "status": "invalid", "_id": "abc123" },
+                {
+                    "email": "cc@example.com",
+                    "status": "rejected",
+                    "reject_reason": "hard-bounce",
+                    "_id": "def456",
+                },
+            ])))
+            .mount(&server)
+            .await;
+
+        let mailer = MandrillMailer::new("test-key").base_url(server.uri());
+
+        let err = mailer.send(&message()).await.unwrap_err();
+        let GenericMailerError::ProviderError(_, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(error.code.as_deref(), Some("rejected"));
+        assert_eq!(
+            error.details,
+            [
+                ("recipient@example.com".to_string(), "invalid".to_string()),
+                ("cc@example.com".to_string(), "hard-bounce".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mandrill_mailer_no_results() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/messages/send"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&server)
+            .await;
+
+        let mailer = MandrillMailer::new("test-key").base_url(server.uri());
+
+        let err = mailer.send(&message()).await.unwrap_err();
+        assert!(matches!(err, GenericMailerError::UnexpectedError(_)));
+    }
+}
@@ -9,12 +9,302 @@ pub mod aws_ses;
 #[cfg(feature = "aws_ses")]
 pub use aws_ses::AwsSesMailer;
 
+#[cfg(feature = "azure_email")]
+pub mod azure_email;
+#[cfg(feature = "azure_email")]
+pub use azure_email::AzureEmailMailer;
+
+#[cfg(feature = "brevo")]
+pub mod brevo;
+#[cfg(feature = "brevo")]
+pub use brevo::BrevoMailer;
+
+#[cfg(feature = "gmail")]
+pub mod gmail;
+#[cfg(feature = "gmail")]
+pub use gmail::GmailMailer;
+
+#[cfg(feature = "mailersend")]
+pub mod mailersend;
+#[cfg(feature = "mailersend")]
+pub use mailersend::MailerSendMailer;
+
+#[cfg(feature = "mailgun")]
+pub mod mailgun;
+#[cfg(feature = "mailgun")]
+pub use mailgun::MailgunMailer;
+
+#[cfg(feature = "mailjet")]
+pub mod mailjet;
+#[cfg(feature = "mailjet")]
+pub use mailjet::MailjetMailer;
+
 #[cfg(feature = "mailtrap")]
 pub mod mailtrap;
 #[cfg(feature = "mailtrap")]
 pub use mailtrap::MailtrapMailer;
 
+#[cfg(feature = "mandrill")]
+pub mod mandrill;
+#[cfg(feature = "mandrill")]
+pub use mandrill::MandrillMailer;
+
+#[cfg(feature = "msgraph")]
+pub mod msgraph;
+#[cfg(feature = "msgraph")]
+pub use msgraph::MsGraphMailer;
+
+#[cfg(feature = "postmark")]
+pub mod postmark;
+#[cfg(feature = "postmark")]
+pub use postmark::PostmarkMailer;
+
+#[cfg(feature = "resend")]
+pub mod resend;
+#[cfg(feature = "resend")]
+pub use resend::ResendMailer;
+
 #[cfg(feature = "sendgrid")]
 pub mod sendgrid;
 #[cfg(feature = "sendgrid")]
 pub use sendgrid::SendgridMailer;
+
+#[cfg(feature = "sparkpost")]
+pub mod sparkpost;
+#[cfg(feature = "sparkpost")]
+pub use sparkpost::SparkPostMailer;
+
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookMailer;
+
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+use std::sync::Mutex;
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+use std::time::Duration;
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+use std::time::Instant;
+
+#[cfg(feature = "__reqwest")]
+use crate::GenericMailerError;
+#[cfg(feature = "__reqwest")]
+use crate::ProviderError;
+
+/// Fails for messages using a provider-hosted template, for mailers that can't send them.
+#[cfg(any(
+    feature = "azure_email",
+    feature = "dkim",
+    feature = "gmail",
+    feature = "mailersend",
+    feature = "mailgun",
+    feature = "mailjet",
+    feature = "mandrill",
+    feature = "msgraph",
+    feature = "pgp",
+    feature = "postmark",
+    feature = "resend",
+    feature = "smime",
+    feature = "sparkpost",
+    feature = "webhook"
+))]
+pub(crate) fn reject_template(
+    mailer: &str,
+    m: &crate::Message,
+) -> Result<(), crate::GenericMailerError> {
+    let Some(template) = &m.template else {
+        return Ok(());
+    };
+
+    let error = format!("{mailer} does not support templates, got: {}", template.id);
+    return Err(crate::GenericMailerError::UnexpectedError(error.into()));
+}
+
+/// A fully read response from a provider's HTTP API.
+#[cfg(feature = "__reqwest")]
+pub(crate) struct ApiResponse {
+    pub status_code: u16,
+    pub text: String,
+}
+
+#[cfg(feature = "__reqwest")]
+impl ApiResponse {
+    /// Reads the response, turning a non-2xx status into an error.
+    ///
+    /// `parse_error` gets the JSON body of the failed request, and if it can't make out
+    /// the provider's error (or the body isn't JSON), the body is kept as-is instead.
+    pub(crate) async fn read(
+        response: reqwest::Response,
+        parse_error: impl FnOnce(&serde_json::Value) -> Option<ProviderError>,
+    ) -> Result<Self, GenericMailerError> {
+        let is_success = response.status().is_success();
+        let response = Self {
+            status_code: response.status().as_u16(),
+            text: response.text().await?,
+        };
+
+        if !is_success {
+            let error = response.json().ok().as_ref().and_then(parse_error);
+            return Err(response.error(error));
+        }
+
+        return Ok(response);
+    }
+
+    pub(crate) fn json(&self) -> Result<serde_json::Value, GenericMailerError> {
+        return serde_json::from_str(&self.text).map_err(|_| self.unexpected());
+    }
+
+    /// The provider's error if it could be made out, the whole response otherwise.
+    pub(crate) fn error(&self, error: Option<ProviderError>) -> GenericMailerError {
+        return match error {
+            Some(error) => GenericMailerError::ProviderError(self.status_code, error),
+            None => self.unexpected(),
+        };
+    }
+
+    pub(crate) fn unexpected(&self) -> GenericMailerError {
+        return GenericMailerError::UnexpectedResponse(self.status_code, self.text.clone());
+    }
+}
+
+/// Caches an OAuth 2.0 access token until (a bit before) it expires.
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+pub(crate) struct TokenCache {
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+impl TokenCache {
+    // Refresh the token a bit before it actually expires
+    const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+    pub(crate) fn new() -> Self {
+        Self {
+            token: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<String> {
+        return match &*self.token.lock().unwrap() {
+            Some((token, expires_at)) if Instant::now() < *expires_at => Some(token.clone()),
+            _ => None,
+        };
+    }
+
+    /// Sends the token request, caching the access token from the response.
+    pub(crate) async fn fetch(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<String, GenericMailerError> {
+        let response = ApiResponse::read(request.send().await?, parse_oauth_error).await?;
+        let json = response.json()?;
+
+        let access_token = json.get("access_token").and_then(|t| t.as_str());
+        let expires_in = json.get("expires_in").and_then(|e| e.as_u64());
+        let (Some(access_token), Some(expires_in)) = (access_token, expires_in) else {
+            return Err(response.unexpected());
+        };
+
+        let expires_in = Duration::from_secs(expires_in).saturating_sub(Self::EXPIRY_MARGIN);
+        *self.token.lock().unwrap() = Some((access_token.to_string(), Instant::now() + expires_in));
+
+        return Ok(access_token.to_string());
+    }
+}
+
+// e.g. { "error": "invalid_client", "error_description": "..." }
+#[cfg(any(feature = "gmail", feature = "msgraph"))]
+fn parse_oauth_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let code = json.get("error")?.as_str()?;
+    let description = json.get("error_description").and_then(|d| d.as_str());
+
+    return Some(ProviderError {
+        code: Some(code.to_string()),
+        message: description.unwrap_or(code).to_string(),
+        details: Vec::new(),
+    });
+}
+
+// SES and Resend tag names and values may only contain ASCII letters, numbers, underscores
+// and dashes, and must be at most 256 chars long. Any other char is replaced with an underscore.
+// See: https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_MessageTag.html
+// See: https://resend.com/docs/api-reference/emails/send-email#body-parameters
+#[cfg(any(feature = "aws_ses", feature = "resend"))]
+pub(crate) fn sanitize_tag(s: &str) -> String {
+    return s
+        .chars()
+        .take(256)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+}
+
+// The sanitized tags, failing if a name ends up empty or the same as another one,
+// since SES and Resend would reject the whole email
+#[cfg(any(feature = "aws_ses", feature = "resend"))]
+pub(crate) fn sanitize_tags<'a>(
+    tags: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<Vec<(String, String)>, crate::GenericMailerError> {
+    let mut sanitized: Vec<(String, String)> = Vec::new();
+
+    for (k, v) in tags {
+        let name = sanitize_tag(k);
+
+        if name.is_empty() {
+            let error = format!("tag name is empty, got: {k:?}");
+            return Err(crate::GenericMailerError::UnexpectedError(error.into()));
+        }
+
+        if sanitized.iter().any(|(other, _)| *other == name) {
+            let error = format!("tag name {name:?} is used more than once, got: {k:?}");
+            return Err(crate::GenericMailerError::UnexpectedError(error.into()));
+        }
+
+        sanitized.push((name, sanitize_tag(v)));
+    }
+
+    return Ok(sanitized);
+}
+
+/// The message the mailer tests start from, with an attachment and a fixed ID and date.
+#[cfg(all(
+    test,
+    any(
+        feature = "azure_email",
+        feature = "brevo",
+        feature = "mailersend",
+        feature = "mailgun",
+        feature = "mailjet",
+        feature = "mailtrap",
+        feature = "mandrill",
+        feature = "msgraph",
+        feature = "postmark",
+        feature = "resend",
+        feature = "sendgrid",
+        feature = "sparkpost",
+        feature = "webhook"
+    )
+))]
+pub(crate) fn test_message() -> crate::MessageBuilder<'static> {
+    return crate::Message::builder()
+        .from(crate::Address::with_name("Sender", "sender@example.com"))
+        .to(crate::Address::with_name(
+            "Recipient",
+            "recipient@example.com",
+        ))
+        .subject("Test Email")
+        .text_body("This is a test email.")
+        .attachment(crate::MessageAttachment::new(
+            "hello.txt",
+            "text/plain",
+            b"Hi!".as_slice(),
+        ))
+        .message_id("<abc123@example.com>")
+        .date(std::time::SystemTime::UNIX_EPOCH);
+}
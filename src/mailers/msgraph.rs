@@ -0,0 +1,343 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::TokenCache;
+use crate::mailers::reject_template;
+
+pub struct MsGraphMailer {
+    client: reqwest::Client,
+    tenant_id: String,
+    client_id: String,
+    client_secret: String,
+    user_id: Option<String>,
+    save_to_sent_items: bool,
+    authority_url: String,
+    base_url: String,
+    token: TokenCache,
+}
+
+impl MsGraphMailer {
+    pub fn new(
+        tenant_id: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self::with_client(reqwest::Client::new(), tenant_id, client_id, client_secret)
+    }
+
+    pub fn with_client(
+        client: reqwest::Client,
+        tenant_id: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            tenant_id: tenant_id.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            user_id: None,
+            save_to_sent_items: true,
+            authority_url: "https://login.microsoftonline.com".to_string(),
+            base_url: "https://graph.microsoft.com".to_string(),
+            token: TokenCache::new(),
+        }
+    }
+
+    /// The user (ID or user principal name) to send as, defaults to the `from` email address.
+    pub fn user_id(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+
+        return self;
+    }
+
+    pub fn save_to_sent_items(mut self, save_to_sent_items: bool) -> Self {
+        self.save_to_sent_items = save_to_sent_items;
+
+        return self;
+    }
+
+    pub fn authority_url(mut self, authority_url: impl Into<String>) -> Self {
+        self.authority_url = authority_url.into();
+
+        return self;
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+
+    // See: https://learn.microsoft.com/en-us/entra/identity-platform/v2-oauth2-client-creds-grant-flow
+    async fn access_token(&self) -> Result<String, GenericMailerError> {
+        if let Some(token) = self.token.get() {
+            return Ok(token);
+        }
+
+        let request = self
+            .client
+            .post(format!(
+                "{}/{}/oauth2/v2.0/token",
+                self.authority_url, self.tenant_id
+            ))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("scope", "https://graph.microsoft.com/.default"),
+            ]);
+
+        return self.token.fetch(request).await;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for MsGraphMailer {
+    // See: https://learn.microsoft.com/en-us/graph/api/user-sendmail
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Microsoft Graph", m)?;
+
+        let access_token = self.access_token().await?;
+        let request = self.build_request(m);
+        let user_id = self.user_id.as_deref().unwrap_or(&m.from.email);
+
+        // The user ID may be a user principal name, so it has to be encoded as a path segment
+        let mut url = reqwest::Url::parse(&self.base_url)
+            .map_err(|err| GenericMailerError::UnexpectedError(Box::new(err)))?;
+        url.path_segments_mut()
+            .map_err(|_| GenericMailerError::UnexpectedError("invalid base URL".into()))?
+            .pop_if_empty()
+            .extend(["v1.0", "users", user_id, "sendMail"]);
+
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(access_token)
+            .json(&request)
+            .send()
+            .await?;
+
+        ApiResponse::read(response, parse_error).await?;
+
+        // NOTE: Graph accepts the message (202) without returning an ID for it,
+        // but it keeps the `internetMessageId` it was given
+        return Ok(m.message_id.iter().map(|id| id.to_string()).collect());
+    }
+}
+
+impl MsGraphMailer {
+    // NOTE: Graph has no equivalent for the metadata, and only allows custom headers
+    // that start with `X-` or `x-`, so any other header is left out.
+    fn build_request(&self, m: &Message) -> serde_json::Value {
+        // Graph only takes a single body, so HTML wins over text if both are given
+        let body = match (&m.text_body, &m.html_body) {
+            (_, Some(html)) => json!({ "contentType": "HTML", "content": html }),
+            (Some(text), None) => json!({ "contentType": "Text", "content": text }),
+            (None, None) => json!({ "contentType": "Text", "content": "" }),
+        };
+
+        let mut message = json!({
+            "from": Self::build_recipient(&m.from),
+            "toRecipients": m.to.iter().map(Self::build_recipient).collect::<serde_json::Value>(),
+            "subject": m.subject,
+            "body": body,
+        });
+
+        if !m.cc.is_empty() {
+            message["ccRecipients"] = m.cc.iter().map(Self::build_recipient).collect();
+        }
+
+        if !m.bcc.is_empty() {
+            message["bccRecipients"] = m.bcc.iter().map(Self::build_recipient).collect();
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            message["replyTo"] = json!([Self::build_recipient(reply_to)]);
+        }
+
+        if !m.attachments.is_empty() {
+            message["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "@odata.type": "#microsoft.graph.fileAttachment",
+                        "name": a.name,
+                        "contentType": a.content_type,
+                        "contentBytes": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let headers: Vec<_> = m
+            .headers
+            .iter()
+            .filter(|(k, _)| k.get(..2).is_some_and(|p| p.eq_ignore_ascii_case("x-")))
+            .map(|(k, v)| json!({ "name": k, "value": v }))
+            .collect();
+
+        if !headers.is_empty() {
+            message["internetMessageHeaders"] = json!(headers);
+        }
+
+        // The date is always set by Graph
+        if let Some(message_id) = &m.message_id {
+            message["internetMessageId"] = json!(message_id);
+        }
+
+        if let Some(category) = &m.category {
+            message["categories"] = json!([category]);
+        }
+
+        return json!({
+            "message": message,
+            "saveToSentItems": self.save_to_sent_items,
+        });
+    }
+
+    fn build_recipient(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "emailAddress": { "address": addr.email, "name": name } })
+        } else {
+            json!({ "emailAddress": { "address": addr.email } })
+        };
+    }
+}
+
+// e.g. { "error": { "code": "ErrorInvalidRecipients", "message": "..." } }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let error = json.get("error")?;
+
+    return Some(ProviderError {
+        code: Some(error.get("code")?.as_str()?.to_string()),
+        message: error.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("Welcome")
+            .headers("X-Test", "yes")
+            .headers("List-Unsubscribe", "<mailto:unsubscribe@example.com>")
+            .html_body("<p>This is a test email.</p>")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_msgraph_mailer() {
+        let expected = json!({
+            "message": {
+                "from": { "emailAddress": { "name": "Sender", "address": "sender@example.com" } },
+                "toRecipients": [
+                    { "emailAddress": { "name": "Recipient", "address": "recipient@example.com" } },
+                ],
+                "subject": "Test Email",
+                "body": { "contentType": "HTML", "content": "<p>This is a test email.</p>" },
+                "attachments": [{
+                    "@odata.type": "#microsoft.graph.fileAttachment",
+                    "name": "hello.txt",
+                    "contentType": "text/plain",
+                    "contentBytes": "SGkh",
+                }],
+                "internetMessageHeaders": [{ "name": "X-Test", "value": "yes" }],
+                "internetMessageId": "<abc123@example.com>",
+                "categories": ["Welcome"],
+            },
+            "saveToSentItems": false,
+        });
+
+        let mailer = MsGraphMailer::new("tenant", "client", "secret").save_to_sent_items(false);
+        let actual = mailer.build_request(&message());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_msgraph_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/tenant/oauth2/v2.0/token"))
+            .and(matchers::body_string_contains(
+                "grant_type=client_credentials",
+            ))
+            .and(matchers::body_string_contains("client_secret=secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "expires_in": 3599,
+                "access_token": "token-123",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v1.0/users/sender@example.com/sendMail"))
+            .and(matchers::header("Authorization", "Bearer token-123"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let mailer = MsGraphMailer::new("tenant", "client", "secret")
+            .authority_url(server.uri())
+            .base_url(server.uri());
+
+        // The second send should reuse the cached token, and Graph returns no ID
+        for _ in 0..2 {
+            let ids = mailer.send(&message()).await.unwrap();
+            assert_eq!(ids, ["<abc123@example.com>"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_msgraph_mailer_send_encodes_user_id() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/tenant/oauth2/v2.0/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "expires_in": 3599,
+                "access_token": "token-123",
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/v1.0/users/a%2Fb%3Fc/sendMail"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mailer = MsGraphMailer::new("tenant", "client", "secret")
+            .user_id("a/b?c")
+            .authority_url(server.uri())
+            .base_url(server.uri());
+
+        mailer.send(&message()).await.unwrap();
+    }
+}
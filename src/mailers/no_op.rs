@@ -8,7 +8,7 @@ pub struct NoOpMailer;
 
 #[async_trait]
 impl GenericMailer for NoOpMailer {
-    async fn send(&self, _: &Message) -> Result<Vec<String>, GenericMailerError> {
-        return Ok(Vec::new());
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        return Ok(m.message_id.iter().map(|id| id.to_string()).collect());
     }
 }
@@ -0,0 +1,288 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct PostmarkMailer {
+    client: reqwest::Client,
+    server_token: String,
+    message_stream: Option<String>,
+    base_url: String,
+}
+
+impl PostmarkMailer {
+    pub fn new(server_token: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), server_token)
+    }
+
+    pub fn with_client(client: reqwest::Client, server_token: impl Into<String>) -> Self {
+        Self {
+            client,
+            server_token: server_token.into(),
+            message_stream: None,
+            base_url: "https://api.postmarkapp.com".to_string(),
+        }
+    }
+
+    // Postmark uses the "outbound" (transactional) stream when none is given
+    pub fn message_stream(mut self, message_stream: impl Into<String>) -> Self {
+        self.message_stream = Some(message_stream.into());
+
+        return self;
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for PostmarkMailer {
+    // See: https://postmarkapp.com/developer/api/email-api#send-a-single-email
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Postmark", m)?;
+
+        let request = self.build_request(m);
+
+        let response = self
+            .client
+            .post(format!("{}/email", self.base_url))
+            .header("X-Postmark-Server-Token", &self.server_token)
+            .header("Accept", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+        let json = response.json()?;
+
+        // NOTE: Postmark reports errors with a non-zero `ErrorCode`,
+        // see: https://postmarkapp.com/developer/api/overview#error-codes
+        if json.get("ErrorCode").and_then(|c| c.as_i64()) != Some(0) {
+            return Err(response.error(parse_error(&json)));
+        }
+
+        let message_id = json.get("MessageID").and_then(|id| id.as_str());
+
+        return Ok(message_id.map(String::from).into_iter().collect());
+    }
+}
+
+impl PostmarkMailer {
+    fn build_request(&self, m: &Message) -> serde_json::Value {
+        let mut req = json!({
+            "From": m.from.to_string(),
+            "To": join_addresses(&m.to),
+            "Subject": m.subject,
+        });
+
+        if !m.cc.is_empty() {
+            req["Cc"] = json!(join_addresses(&m.cc));
+        }
+
+        if !m.bcc.is_empty() {
+            req["Bcc"] = json!(join_addresses(&m.bcc));
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            req["ReplyTo"] = json!(reply_to.to_string());
+        }
+
+        if let Some(body) = &m.text_body {
+            req["TextBody"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            req["HtmlBody"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            req["Attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "Name": a.name,
+                        "Content": BASE64_STANDARD.encode(&a.bytes),
+                        "ContentType": a.content_type,
+                    })
+                })
+                .collect();
+        }
+
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            req["Headers"] = headers
+                .iter()
+                .map(|(k, v)| json!({ "Name": k, "Value": v }))
+                .collect();
+        }
+
+        if let Some(category) = &m.category {
+            req["Tag"] = json!(category);
+        }
+
+        if !m.metadata.is_empty() {
+            let map = serde_json::Map::from_iter(
+                m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))),
+            );
+            req["Metadata"] = serde_json::Value::from(map);
+        }
+
+        if let Some(message_stream) = &self.message_stream {
+            req["MessageStream"] = json!(message_stream);
+        }
+
+        return req;
+    }
+}
+
+fn join_addresses(addrs: &[Address]) -> String {
+    return addrs
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+}
+
+// e.g. { "ErrorCode": 300, "Message": "Invalid 'From' address: ..." }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let code = json.get("ErrorCode")?.as_i64()?;
+    let message = json.get("Message").and_then(|m| m.as_str());
+
+    return Some(ProviderError {
+        code: Some(code.to_string()),
+        message: message.unwrap_or_default().to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::MessageTemplate;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .reply_to("reply@example.com")
+            .to("other@example.com")
+            .cc(Address::new("cc@example.com"))
+            .headers("X-Test", "yes")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_postmark_mailer() {
+        let expected = json!({
+            "From": "\"Sender\" <sender@example.com>",
+            "To": "\"Recipient\" <recipient@example.com>, other@example.com",
+            "Cc": "cc@example.com",
+            "ReplyTo": "reply@example.com",
+            "Subject": "Test Email",
+            "TextBody": "This is a test email.",
+            "Attachments": [
+                { "Name": "hello.txt", "Content": "SGkh", "ContentType": "text/plain" },
+            ],
+            "Headers": [
+                { "Name": "Message-ID", "Value": "<abc123@example.com>" },
+                { "Name": "Date", "Value": "Thu, 01 Jan 1970 00:00:00 +0000" },
+                { "Name": "X-Test", "Value": "yes" },
+            ],
+            "Tag": "welcome",
+            "Metadata": { "user_id": "42" },
+            "MessageStream": "broadcast",
+        });
+
+        let mailer = PostmarkMailer::new("token").message_stream("broadcast");
+        let actual = mailer.build_request(&message());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_postmark_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/email"))
+            .and(matchers::header("X-Postmark-Server-Token", "token"))
+            .and(matchers::body_partial_json(
+                json!({ "Subject": "Test Email" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "To": "recipient@example.com",
+                "SubmittedAt": "2024-01-01T00:00:00.0000000-05:00",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mailer = PostmarkMailer::new("token").base_url(server.uri());
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert_eq!(ids, vec!["b7bc2f4a-e38e-4336-af7d-e6c392c2f817"]);
+    }
+
+    #[tokio::test]
+    async fn test_postmark_mailer_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/email"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "ErrorCode": 300,
+                "Message": "Invalid email request",
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = PostmarkMailer::new("token").base_url(server.uri());
+        let err = mailer.send(&message()).await.unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 422);
+        assert_eq!(error.code.as_deref(), Some("300"));
+        assert_eq!(error.message, "Invalid email request");
+    }
+
+    #[tokio::test]
+    async fn test_postmark_mailer_template() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .template(MessageTemplate::new("welcome"))
+            .build()
+            .unwrap();
+
+        // Templates aren't supported, so this fails before sending anything
+        let mailer = PostmarkMailer::new("test-token").base_url("http://127.0.0.1:9");
+        let err = mailer.send(&message).await.unwrap_err();
+
+        assert!(matches!(err, GenericMailerError::UnexpectedError(_)));
+    }
+}
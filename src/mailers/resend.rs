@@ -0,0 +1,258 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+use crate::mailers::sanitize_tags;
+
+pub struct ResendMailer {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl ResendMailer {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key)
+    }
+
+    pub fn with_client(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: "https://api.resend.com".to_string(),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for ResendMailer {
+    // See: https://resend.com/docs/api-reference/emails/send-email
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("Resend", m)?;
+
+        let request = Self::build_request(m)?;
+
+        let response = self
+            .client
+            .post(format!("{}/emails", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+        let json = response.json()?;
+
+        let Some(id) = json.get("id").and_then(|id| id.as_str()) else {
+            return Err(response.unexpected());
+        };
+
+        return Ok(vec![id.to_string()]);
+    }
+}
+
+impl ResendMailer {
+    fn build_request(m: &Message) -> Result<serde_json::Value, GenericMailerError> {
+        let mut req = json!({
+            "from": m.from.to_string(),
+            "to": Self::build_addresses(&m.to),
+            "subject": m.subject,
+        });
+
+        if !m.cc.is_empty() {
+            req["cc"] = Self::build_addresses(&m.cc);
+        }
+
+        if !m.bcc.is_empty() {
+            req["bcc"] = Self::build_addresses(&m.bcc);
+        }
+
+        if let Some(reply_to) = &m.reply_to {
+            req["reply_to"] = json!(reply_to.to_string());
+        }
+
+        if let Some(body) = &m.text_body {
+            req["text"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            req["html"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            req["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "filename": a.name,
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                        "content_type": a.content_type,
+                    })
+                })
+                .collect();
+        }
+
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let map =
+                serde_json::Map::from_iter(headers.iter().map(|(k, v)| (k.to_string(), json!(v))));
+            req["headers"] = serde_json::Value::from(map);
+        }
+
+        // NOTE: Resend only has tags, so both the category and the metadata end up there,
+        // sanitized since tags only allow a restricted set of chars.
+        let category = m.category.as_deref().map(|category| ("category", category));
+        let metadata = m.metadata.iter().map(|(k, v)| (k.as_ref(), v.as_ref()));
+        let tags = sanitize_tags(category.into_iter().chain(metadata))?;
+
+        if !tags.is_empty() {
+            req["tags"] = tags
+                .iter()
+                .map(|(k, v)| json!({ "name": k, "value": v }))
+                .collect();
+        }
+
+        return Ok(req);
+    }
+
+    fn build_addresses(addrs: &[Address]) -> serde_json::Value {
+        return addrs.iter().map(|a| json!(a.to_string())).collect();
+    }
+}
+
+// e.g. { "statusCode": 422, "name": "validation_error", "message": "..." }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    return Some(ProviderError {
+        code: json.get("name").and_then(|n| n.as_str()).map(String::from),
+        message: json.get("message")?.as_str()?.to_string(),
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .metadata("plan.name", "Pro Plus")
+            .cc(Address::new("cc@example.com"))
+            .headers("X-Test", "yes")
+            .html_body("<p>This is a test email.</p>")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_resend_mailer() {
+        let expected = json!({
+            "from": "\"Sender\" <sender@example.com>",
+            "to": ["\"Recipient\" <recipient@example.com>"],
+            "cc": ["cc@example.com"],
+            "subject": "Test Email",
+            "text": "This is a test email.",
+            "html": "<p>This is a test email.</p>",
+            "attachments": [
+                { "filename": "hello.txt", "content": "SGkh", "content_type": "text/plain" },
+            ],
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                "X-Test": "yes",
+            },
+            "tags": [
+                { "name": "category", "value": "welcome" },
+                { "name": "user_id", "value": "42" },
+                { "name": "plan_name", "value": "Pro_Plus" },
+            ],
+        });
+
+        let actual = ResendMailer::build_request(&message()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_resend_mailer_colliding_tags() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .metadata("plan.name", "Pro")
+            .metadata("plan/name", "Plus")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        assert!(ResendMailer::build_request(&message).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resend_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/emails"))
+            .and(matchers::header("Authorization", "Bearer re_123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "49a3999c-0ce1-4ea6-ab68-afcd6dc2e794",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mailer = ResendMailer::new("re_123").base_url(server.uri());
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert_eq!(ids, vec!["49a3999c-0ce1-4ea6-ab68-afcd6dc2e794"]);
+    }
+
+    #[tokio::test]
+    async fn test_resend_mailer_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/emails"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "statusCode": 422,
+                "name": "validation_error",
+                "message": "Invalid `to` field.",
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = ResendMailer::new("re_123").base_url(server.uri());
+        let err = mailer.send(&message()).await.unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 422);
+        assert_eq!(error.code.as_deref(), Some("validation_error"));
+        assert_eq!(error.message, "Invalid `to` field.");
+    }
+}
@@ -1,10 +1,14 @@
 use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
 use serde_json::json;
 
 use crate::Address;
 use crate::GenericMailer;
 use crate::GenericMailerError;
 use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
 
 pub struct SendgridMailer {
     client: reqwest::Client,
@@ -41,13 +45,6 @@ impl GenericMailer for SendgridMailer {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let status_code = response.status().as_u16();
-            let body = response.text().await?;
-
-            return Err(GenericMailerError::UnexpectedResponse(status_code, body));
-        }
-
         // NOTE: x-message-id is not the same as the message ID,
         // but the message ID is prefixed with the x-message-id.
         // https://www.twilio.com/docs/sendgrid/glossary/what-is-x-message-id
@@ -58,6 +55,8 @@ impl GenericMailer for SendgridMailer {
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string());
 
+        ApiResponse::read(response, parse_error).await?;
+
         return Ok(x_message_id.into_iter().collect());
     }
 }
@@ -67,18 +66,45 @@ impl SendgridMailer {
         let mut req = json!({
             "from": Self::build_address(&m.from),
             "personalizations": [Self::build_personalization(m)],
-            "subject": m.subject,
-            "content": Self::build_content(m),
         });
 
+        // With a template, the subject and content are optional overrides
+        if m.template.is_none() || !m.subject.is_empty() {
+            req["subject"] = json!(m.subject);
+        }
+
+        let content = Self::build_content(m);
+        if m.template.is_none() || !content.is_empty() {
+            req["content"] = json!(content);
+        }
+
+        if let Some(template) = &m.template {
+            req["template_id"] = json!(template.id);
+        }
+
         if let Some(reply_to) = &m.reply_to {
             req["reply_to"] = Self::build_address(reply_to);
         }
 
-        if !m.headers.is_empty() {
-            let map = serde_json::Map::from_iter(
-                m.headers.iter().map(|(k, v)| (k.to_string(), json!(v))),
-            );
+        if !m.attachments.is_empty() {
+            req["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "content": BASE64_STANDARD.encode(&a.bytes),
+                        "type": a.content_type,
+                        "filename": a.name,
+                    })
+                })
+                .collect();
+        }
+
+        let headers = m.all_headers();
+
+        if !headers.is_empty() {
+            let map =
+                serde_json::Map::from_iter(headers.iter().map(|(k, v)| (k.to_string(), json!(v))));
             req["headers"] = serde_json::Value::from(map);
         }
 
@@ -109,6 +135,10 @@ impl SendgridMailer {
             personalization["bcc"] = m.bcc.iter().map(Self::build_address).collect();
         }
 
+        if let Some(template) = &m.template {
+            personalization["dynamic_template_data"] = template.variables_json();
+        }
+
         return personalization;
     }
 
@@ -141,18 +171,39 @@ impl SendgridMailer {
     }
 }
 
+// e.g. { "errors": [{ "message": "...", "field": "from.email", "help": null }] }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let errors = json.get("errors")?.as_array()?;
+    let message = errors.first()?.get("message")?.as_str()?;
+
+    let details = errors
+        .iter()
+        .filter_map(|e| {
+            let field = e.get("field")?.as_str()?;
+            let message = e.get("message")?.as_str()?;
+            return Some((field.to_string(), message.to_string()));
+        })
+        .collect();
+
+    return Some(ProviderError {
+        code: None,
+        message: message.to_string(),
+        details,
+    });
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::SystemTime;
+
     use super::*;
+    use crate::MessageTemplate;
+    use crate::mailers::test_message;
 
     #[test]
     fn test_sendgrid_mailer() {
-        let message = Message::builder()
-            .from(Address::with_name("Sender", "sender@example.com"))
-            .to(Address::with_name("Recipient", "recipient@example.com"))
+        let message = test_message()
             .cc(Address::new("cc@example.com"))
-            .subject("Test Email")
-            .text_body("This is a test email.")
             .build()
             .unwrap();
 
@@ -168,6 +219,46 @@ mod tests {
             "content": [
                 { "type": "text/plain", "value": "This is a test email." },
             ],
+            "attachments": [{ "content": "SGkh", "type": "text/plain", "filename": "hello.txt" }],
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+            },
+        });
+
+        let actual = SendgridMailer::build_request(&message);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_sendgrid_mailer_template() {
+        let message = Message::builder()
+            .from(Address::new("sender@example.com"))
+            .to(Address::new("recipient@example.com"))
+            .template(
+                MessageTemplate::new("d-0123456789abcdef")
+                    .variable("name", "Recipient")
+                    .variable("items", vec!["a", "b"]),
+            )
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "from": { "email": "sender@example.com" },
+            "personalizations": [
+                {
+                    "to": [{ "email": "recipient@example.com" }],
+                    "dynamic_template_data": { "name": "Recipient", "items": ["a", "b"] },
+                },
+            ],
+            "template_id": "d-0123456789abcdef",
+            "headers": {
+                "Message-ID": "<abc123@example.com>",
+                "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+            },
         });
 
         let actual = SendgridMailer::build_request(&message);
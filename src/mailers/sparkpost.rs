@@ -0,0 +1,395 @@
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use serde_json::json;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::ProviderError;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub struct SparkPostMailer {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparkPostRegion {
+    Us,
+    Eu,
+}
+
+impl SparkPostRegion {
+    fn base_url(self) -> &'static str {
+        return match self {
+            SparkPostRegion::Us => "https://api.sparkpost.com",
+            SparkPostRegion::Eu => "https://api.eu.sparkpost.com",
+        };
+    }
+}
+
+impl SparkPostMailer {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), api_key)
+    }
+
+    pub fn with_client(client: reqwest::Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+            base_url: SparkPostRegion::Us.base_url().to_string(),
+        }
+    }
+
+    pub fn region(mut self, region: SparkPostRegion) -> Self {
+        self.base_url = region.base_url().to_string();
+
+        return self;
+    }
+
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+
+        return self;
+    }
+
+    /// Sends the message with per-recipient substitution data, keyed by the `to` email address.
+    pub async fn send_with_substitution_data(
+        &self,
+        m: &Message<'_>,
+        substitution_data: &[(&str, serde_json::Value)],
+    ) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("SparkPost", m)?;
+
+        let request = Self::build_request(m, substitution_data);
+
+        return self.post(&request).await;
+    }
+
+    async fn post(&self, request: &serde_json::Value) -> Result<Vec<String>, GenericMailerError> {
+        let response = self
+            .client
+            .post(format!("{}/api/v1/transmissions", self.base_url))
+            .header("Authorization", &self.api_key)
+            .json(request)
+            .send()
+            .await?;
+
+        let response = ApiResponse::read(response, parse_error).await?;
+
+        // e.g. { "results": { "total_accepted_recipients": 1, "id": "..." } }
+        let id = response.json().ok().and_then(|json| {
+            return Some(json.pointer("/results/id")?.as_str()?.to_string());
+        });
+
+        return Ok(id.into_iter().collect());
+    }
+}
+
+#[async_trait]
+impl GenericMailer for SparkPostMailer {
+    // See: https://developers.sparkpost.com/api/transmissions/#transmissions-post-send-inline-content
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("SparkPost", m)?;
+
+        let request = Self::build_request(m, &[]);
+
+        return self.post(&request).await;
+    }
+}
+
+impl SparkPostMailer {
+    fn build_request(
+        m: &Message,
+        substitution_data: &[(&str, serde_json::Value)],
+    ) -> serde_json::Value {
+        let mut req = json!({
+            "recipients": Self::build_recipients(m, substitution_data),
+            "content": Self::build_content(m),
+        });
+
+        if let Some(category) = &m.category {
+            req["campaign_id"] = json!(category);
+        }
+
+        if !m.metadata.is_empty() {
+            let map = serde_json::Map::from_iter(
+                m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))),
+            );
+            req["metadata"] = serde_json::Value::from(map);
+        }
+
+        return req;
+    }
+
+    // NOTE: SparkPost has no notion of cc and bcc, instead every recipient
+    // gets `header_to` set to the actual recipients, and cc is just a header.
+    // See: https://www.sparkpost.com/docs/faq/cc-bcc-with-rest-api/
+    fn build_recipients(
+        m: &Message,
+        substitution_data: &[(&str, serde_json::Value)],
+    ) -> Vec<serde_json::Value> {
+        let mut recipients = Vec::with_capacity(m.to.len() + m.cc.len() + m.bcc.len());
+
+        for addr in &m.to {
+            let mut recipient = json!({ "address": Self::build_address(addr) });
+
+            let data = substitution_data
+                .iter()
+                .find(|(email, _)| *email == addr.email);
+            if let Some((_, data)) = data {
+                recipient["substitution_data"] = data.clone();
+            }
+
+            recipients.push(recipient);
+        }
+
+        let header_to = join_addresses(&m.to);
+        for addr in m.cc.iter().chain(&m.bcc) {
+            recipients.push(json!({
+                "address": { "email": addr.email, "header_to": header_to },
+            }));
+        }
+
+        return recipients;
+    }
+
+    fn build_content(m: &Message) -> serde_json::Value {
+        let mut content = json!({
+            "from": Self::build_address(&m.from),
+            "subject": m.subject,
+        });
+
+        if let Some(reply_to) = &m.reply_to {
+            content["reply_to"] = json!(reply_to.to_string());
+        }
+
+        if let Some(body) = &m.text_body {
+            content["text"] = json!(body);
+        }
+
+        if let Some(body) = &m.html_body {
+            content["html"] = json!(body);
+        }
+
+        if !m.attachments.is_empty() {
+            content["attachments"] = m
+                .attachments
+                .iter()
+                .map(|a| {
+                    json!({
+                        "name": a.name,
+                        "type": a.content_type,
+                        "data": BASE64_STANDARD.encode(&a.bytes),
+                    })
+                })
+                .collect();
+        }
+
+        let mut headers = serde_json::Map::from_iter(
+            m.all_headers()
+                .iter()
+                .map(|(k, v)| (k.to_string(), json!(v))),
+        );
+
+        if !m.cc.is_empty() {
+            headers.insert("CC".to_string(), json!(join_addresses(&m.cc)));
+        }
+
+        if !headers.is_empty() {
+            content["headers"] = serde_json::Value::from(headers);
+        }
+
+        return content;
+    }
+
+    fn build_address(addr: &Address) -> serde_json::Value {
+        return if let Some(name) = &addr.name {
+            json!({ "email": addr.email, "name": name })
+        } else {
+            json!({ "email": addr.email })
+        };
+    }
+}
+
+fn join_addresses(addrs: &[Address]) -> String {
+    return addrs
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+}
+
+// e.g. { "errors": [{ "message": "...", "description": "...", "code": "1902" }] }
+fn parse_error(json: &serde_json::Value) -> Option<ProviderError> {
+    let error = json.pointer("/errors/0")?;
+
+    let message = error.get("message").and_then(|m| m.as_str());
+    let description = error.get("description").and_then(|d| d.as_str());
+    let message = match (message, description) {
+        (Some(message), Some(description)) => format!("{message}: {description}"),
+        (Some(message), None) => message.to_string(),
+        (None, Some(description)) => description.to_string(),
+        (None, None) => return None,
+    };
+
+    return Some(ProviderError {
+        code: error.get("code").and_then(|c| c.as_str()).map(String::from),
+        message,
+        details: Vec::new(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    #[test]
+    fn test_sparkpost_mailer() {
+        let message = test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .cc(Address::new("cc@example.com"))
+            .bcc(Address::new("bcc@example.com"))
+            .subject("Hi {{name}}")
+            .build()
+            .unwrap();
+
+        let expected = json!({
+            "campaign_id": "welcome",
+            "metadata": { "user_id": "42" },
+            "recipients": [
+                {
+                    "address": { "name": "Recipient", "email": "recipient@example.com" },
+                    "substitution_data": { "name": "Recipient" },
+                },
+                {
+                    "address": {
+                        "email": "cc@example.com",
+                        "header_to": "\"Recipient\" <recipient@example.com>",
+                    },
+                },
+                {
+                    "address": {
+                        "email": "bcc@example.com",
+                        "header_to": "\"Recipient\" <recipient@example.com>",
+                    },
+                },
+            ],
+            "content": {
+                "from": { "name": "Sender", "email": "sender@example.com" },
+                "subject": "Hi {{name}}",
+                "text": "This is a test email.",
+                "attachments": [{ "name": "hello.txt", "type": "text/plain", "data": "SGkh" }],
+                "headers": {
+                    "Message-ID": "<abc123@example.com>",
+                    "Date": "Thu, 01 Jan 1970 00:00:00 +0000",
+                    "CC": "cc@example.com",
+                },
+            },
+        });
+
+        let substitution_data = [("recipient@example.com", json!({ "name": "Recipient" }))];
+        let actual = SparkPostMailer::build_request(&message, &substitution_data);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn test_sparkpost_mailer_send() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/api/v1/transmissions"))
+            .and(matchers::header("Authorization", "key"))
+            .and(matchers::body_partial_json(json!({
+                "recipients": [{ "substitution_data": { "name": "Recipient" } }],
+                "content": { "subject": "Test Email" },
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": {
+                    "total_rejected_recipients": 0,
+                    "total_accepted_recipients": 1,
+                    "id": "11668787484950529",
+                },
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mailer = SparkPostMailer::new("key").base_url(server.uri());
+        let substitution_data = [("recipient@example.com", json!({ "name": "Recipient" }))];
+        let ids = mailer
+            .send_with_substitution_data(&test_message().build().unwrap(), &substitution_data)
+            .await
+            .unwrap();
+
+        assert_eq!(ids, ["11668787484950529"]);
+    }
+
+    #[tokio::test]
+    async fn test_sparkpost_mailer_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/api/v1/transmissions"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "errors": [{
+                    "message": "Message generation rejected",
+                    "description": "recipient address suppressed due to customer policy",
+                    "code": "1902",
+                }],
+            })))
+            .mount(&server)
+            .await;
+
+        let mailer = SparkPostMailer::new("key").base_url(server.uri());
+        let err = mailer
+            .send(&test_message().build().unwrap())
+            .await
+            .unwrap_err();
+
+        let GenericMailerError::ProviderError(status, error) = err else {
+            panic!("Expected a provider error, got: {err}");
+        };
+        assert_eq!(status, 400);
+        assert_eq!(error.code.as_deref(), Some("1902"));
+        assert_eq!(
+            error.message,
+            "Message generation rejected: recipient address suppressed due to customer policy"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sparkpost_mailer_unexpected_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/api/v1/transmissions"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+            .mount(&server)
+            .await;
+
+        let mailer = SparkPostMailer::new("key").base_url(server.uri());
+        let err = mailer
+            .send(&test_message().build().unwrap())
+            .await
+            .unwrap_err();
+
+        let GenericMailerError::UnexpectedResponse(status, body) = err else {
+            panic!("Expected an unexpected response, got: {err}");
+        };
+        assert_eq!(status, 503);
+        assert_eq!(body, "Service Unavailable");
+    }
+}
@@ -0,0 +1,289 @@
+//! Sends messages as JSON POST requests to a URL of your choosing.
+//!
+//! The payload looks like this, with optional fields set to `null` when not given:
+//!
+//! ```json
+//! {
+//!   "from": { "email": "sender@example.com", "name": "Sender" },
+//!   "reply_to": null,
+//!   "to": [{ "email": "recipient@example.com", "name": null }],
+//!   "cc": [],
+//!   "bcc": [],
+//!   "subject": "Hello",
+//!   "text_body": "Hello, world!",
+//!   "html_body": null,
+//!   "headers": [
+//!     { "name": "Message-ID", "value": "<67e55044-10b1-426f-9247-bb680e5fe0c8@example.com>" },
+//!     { "name": "Date", "value": "Sun, 06 Nov 1994 08:49:37 +0000" },
+//!     { "name": "X-Campaign", "value": "launch" }
+//!   ],
+//!   "category": "welcome",
+//!   "metadata": { "user_id": "42" },
+//!   "attachments": [{ "name": "hello.txt", "content_type": "text/plain", "content": "SGkh" }]
+//! }
+//! ```
+//!
+//! The attachment `content` is base64 encoded. The endpoint is expected to respond
+//! with a 2xx status, optionally with a JSON object with an `id` field, e.g. `{ "id": "abc123" }`.
+//!
+//! When a signing secret is set, the request includes an `X-Webhook-Signature` header
+//! with the hex encoded HMAC-SHA256 of the body, i.e. `sha256=<hex digest>`.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+use hmac::Hmac;
+use hmac::Mac;
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::Address;
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::mailers::ApiResponse;
+use crate::mailers::reject_template;
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+pub struct WebhookMailer {
+    client: reqwest::Client,
+    url: String,
+    signing_secret: Option<Vec<u8>>,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+}
+
+impl WebhookMailer {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), url)
+    }
+
+    pub fn with_client(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            signing_secret: None,
+            headers: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn signing_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.signing_secret = Some(secret.into());
+
+        return self;
+    }
+
+    /// Adds a header to every request, e.g. for authentication.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+
+        return self;
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+
+        return self;
+    }
+}
+
+#[async_trait]
+impl GenericMailer for WebhookMailer {
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("The webhook mailer", m)?;
+
+        let body = build_payload(m).to_string();
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+
+        for (k, v) in &self.headers {
+            request = request.header(k, v);
+        }
+
+        if let Some(secret) = &self.signing_secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+        }
+
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let response = request.body(body).send().await?;
+
+        // The receiving end is ours, so there is no error format to parse
+        let response = ApiResponse::read(response, |_| None).await?;
+
+        // NOTE: The receiver may not track messages, so the ID is optional and can be a number
+        let id = response.json().ok().and_then(|json| {
+            return match json.get("id")? {
+                serde_json::Value::String(id) => Some(id.to_string()),
+                serde_json::Value::Number(id) => Some(id.to_string()),
+                _ => None,
+            };
+        });
+
+        return Ok(id.into_iter().collect());
+    }
+}
+
+fn build_payload(m: &Message) -> serde_json::Value {
+    let metadata =
+        serde_json::Map::from_iter(m.metadata.iter().map(|(k, v)| (k.to_string(), json!(v))));
+
+    return json!({
+        "from": build_address(&m.from),
+        "reply_to": m.reply_to.as_ref().map(build_address),
+        "to": m.to.iter().map(build_address).collect::<Vec<_>>(),
+        "cc": m.cc.iter().map(build_address).collect::<Vec<_>>(),
+        "bcc": m.bcc.iter().map(build_address).collect::<Vec<_>>(),
+        "subject": m.subject,
+        "text_body": m.text_body,
+        "html_body": m.html_body,
+        "headers": m
+            .all_headers()
+            .iter()
+            .map(|(k, v)| json!({ "name": k, "value": v }))
+            .collect::<Vec<_>>(),
+        "category": m.category,
+        "metadata": metadata,
+        "attachments": m
+            .attachments
+            .iter()
+            .map(|a| {
+                json!({
+                    "name": a.name,
+                    "content_type": a.content_type,
+                    "content": BASE64_STANDARD.encode(&a.bytes),
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+}
+
+fn build_address(addr: &Address) -> serde_json::Value {
+    return json!({ "email": addr.email, "name": addr.name });
+}
+
+fn sign(secret: &[u8], body: &[u8]) -> String {
+    // HMAC accepts keys of any length, so this never fails
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(body);
+
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+
+    return format!("sha256={hex}");
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers;
+
+    use super::*;
+    use crate::mailers::test_message;
+
+    fn message() -> Message<'static> {
+        return test_message()
+            .category("welcome")
+            .metadata("user_id", "42")
+            .headers("X-Campaign", "launch")
+            .build()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_webhook_payload() {
+        let expected = json!({
+            "from": { "email": "sender@example.com", "name": "Sender" },
+            "reply_to": null,
+            "to": [{ "email": "recipient@example.com", "name": "Recipient" }],
+            "cc": [],
+            "bcc": [],
+            "subject": "Test Email",
+            "text_body": "This is a test email.",
+            "html_body": null,
+            "headers": [
+                { "name": "Message-ID", "value": "<abc123@example.com>" },
+                { "name": "Date", "value": "Thu, 01 Jan 1970 00:00:00 +0000" },
+                { "name": "X-Campaign", "value": "launch" },
+            ],
+            "category": "welcome",
+            "metadata": { "user_id": "42" },
+            "attachments": [{ "name": "hello.txt", "content_type": "text/plain", "content": "SGkh" }],
+        });
+
+        let actual = build_payload(&message());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_webhook_sign() {
+        // See: https://datatracker.ietf.org/doc/html/rfc4231#section-4.3
+        let actual = sign(b"Jefe", b"what do ya want for nothing?");
+
+        assert_eq!(
+            actual,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_mailer_send() {
+        let server = MockServer::start().await;
+        let expected_signature = sign(b"secret", build_payload(&message()).to_string().as_bytes());
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/relay"))
+            .and(matchers::header("Authorization", "Bearer test-token"))
+            .and(matchers::header(
+                SIGNATURE_HEADER,
+                expected_signature.as_str(),
+            ))
+            .and(matchers::body_partial_json(
+                json!({ "subject": "Test Email" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "abc123" })))
+            .mount(&server)
+            .await;
+
+        let mailer = WebhookMailer::new(format!("{}/relay", server.uri()))
+            .signing_secret("secret")
+            .header("Authorization", "Bearer test-token")
+            .timeout(Duration::from_secs(5));
+
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert_eq!(ids, ["abc123"]);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_mailer_send_without_id() {
+        let server = MockServer::start().await;
+
+        Mock::given(matchers::method("POST"))
+            .and(matchers::path("/relay"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let mailer = WebhookMailer::new(format!("{}/relay", server.uri()));
+        let ids = mailer.send(&message()).await.unwrap();
+
+        assert!(ids.is_empty());
+    }
+}
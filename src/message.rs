@@ -1,9 +1,13 @@
 use std::borrow::Cow;
 use std::fmt;
+use std::time::SystemTime;
 
 use super::Address;
+use super::html;
+use super::utils;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message<'a> {
     pub category: Option<Cow<'a, str>>,
     pub metadata: Vec<(Cow<'a, str>, Cow<'a, str>)>,
@@ -12,25 +16,316 @@ pub struct Message<'a> {
     pub to: Vec<Address<'a>>,
     pub cc: Vec<Address<'a>>,
     pub bcc: Vec<Address<'a>>,
+    // NOTE: Generated by the builder unless given, and only sent along with the custom headers
+    // when given, as not every provider accepts them (e.g. MailerSend's free plan)
+    pub message_id: Option<Cow<'a, str>>,
+    pub date: Option<SystemTime>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub custom_message_id: bool,
     pub headers: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     pub subject: Cow<'a, str>,
     pub text_body: Option<Cow<'a, str>>,
     pub html_body: Option<Cow<'a, str>>,
+    // NOTE: Only sent by mailers that render raw MIME (e.g. `AwsSesMailer`) and the `WebhookMailer`
+    pub attachments: Vec<MessageAttachment<'a>>,
+    // NOTE: Only sent by `SendgridMailer`, `MailtrapMailer` and `AwsSesMailer`
+    pub template: Option<MessageTemplate<'a>>,
     // TODO:
-    // pub attachments: Vec<MessageAttachment<'a>>,
     // pub inline_attachments: Vec<MessageAttachment<'a>>,
 }
 
-// pub struct MessageAttachment<'a> {
-//     pub name: Cow<'a, str>,
-//     pub content_type: mime::Mime,
-//     pub bytes: Cow<'a, [u8]>,
-// }
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageAttachment<'a> {
+    pub name: Cow<'a, str>,
+    pub content_type: Cow<'a, str>,
+    pub bytes: Cow<'a, [u8]>,
+}
+
+// A template stored by the provider, which fills in the subject and body when they're left empty
+// Only SES, SendGrid, Mailtrap and Brevo support them, the other mailers fail to send
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageTemplate<'a> {
+    pub id: Cow<'a, str>,
+    pub variables: Vec<(Cow<'a, str>, TemplateValue<'a>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TemplateValue<'a> {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(Cow<'a, str>),
+    List(Vec<TemplateValue<'a>>),
+    Map(Vec<(Cow<'a, str>, TemplateValue<'a>)>),
+}
+
+impl<'a> MessageAttachment<'a> {
+    pub fn new(
+        name: impl Into<Cow<'a, str>>,
+        content_type: impl Into<Cow<'a, str>>,
+        bytes: impl Into<Cow<'a, [u8]>>,
+    ) -> Self {
+        return Self {
+            name: name.into(),
+            content_type: content_type.into(),
+            bytes: bytes.into(),
+        };
+    }
+
+    pub fn into_owned(self) -> MessageAttachment<'static> {
+        return MessageAttachment {
+            name: into_owned_str(self.name),
+            content_type: into_owned_str(self.content_type),
+            bytes: Cow::Owned(self.bytes.into_owned()),
+        };
+    }
+}
+
+impl<'a> MessageTemplate<'a> {
+    pub fn new(id: impl Into<Cow<'a, str>>) -> Self {
+        return Self {
+            id: id.into(),
+            variables: Vec::new(),
+        };
+    }
+
+    pub fn variable(
+        mut self,
+        key: impl Into<Cow<'a, str>>,
+        value: impl Into<TemplateValue<'a>>,
+    ) -> Self {
+        self.variables.push((key.into(), value.into()));
+
+        return self;
+    }
+
+    pub fn into_owned(self) -> MessageTemplate<'static> {
+        return MessageTemplate {
+            id: into_owned_str(self.id),
+            variables: self
+                .variables
+                .into_iter()
+                .map(|(k, v)| (into_owned_str(k), v.into_owned()))
+                .collect(),
+        };
+    }
+
+    #[cfg(any(
+        feature = "aws_ses",
+        feature = "brevo",
+        feature = "mailtrap",
+        feature = "sendgrid"
+    ))]
+    pub(crate) fn variables_json(&self) -> serde_json::Value {
+        return TemplateValue::map_to_json(&self.variables);
+    }
+}
+
+impl TemplateValue<'_> {
+    pub fn into_owned(self) -> TemplateValue<'static> {
+        return match self {
+            TemplateValue::Null => TemplateValue::Null,
+            TemplateValue::Bool(b) => TemplateValue::Bool(b),
+            TemplateValue::Integer(n) => TemplateValue::Integer(n),
+            TemplateValue::Float(n) => TemplateValue::Float(n),
+            TemplateValue::String(s) => TemplateValue::String(into_owned_str(s)),
+            TemplateValue::List(items) => {
+                TemplateValue::List(items.into_iter().map(TemplateValue::into_owned).collect())
+            }
+            TemplateValue::Map(entries) => TemplateValue::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (into_owned_str(k), v.into_owned()))
+                    .collect(),
+            ),
+        };
+    }
+
+    #[cfg(any(
+        feature = "aws_ses",
+        feature = "brevo",
+        feature = "mailtrap",
+        feature = "sendgrid"
+    ))]
+    fn to_json(&self) -> serde_json::Value {
+        return match self {
+            TemplateValue::Null => serde_json::Value::Null,
+            TemplateValue::Bool(b) => serde_json::Value::from(*b),
+            TemplateValue::Integer(n) => serde_json::Value::from(*n),
+            TemplateValue::Float(n) => serde_json::Value::from(*n),
+            TemplateValue::String(s) => serde_json::Value::from(s.as_ref()),
+            TemplateValue::List(items) => items.iter().map(TemplateValue::to_json).collect(),
+            TemplateValue::Map(entries) => TemplateValue::map_to_json(entries),
+        };
+    }
+
+    #[cfg(any(
+        feature = "aws_ses",
+        feature = "brevo",
+        feature = "mailtrap",
+        feature = "sendgrid"
+    ))]
+    pub(crate) fn map_to_json(entries: &[(Cow<'_, str>, TemplateValue<'_>)]) -> serde_json::Value {
+        let map =
+            serde_json::Map::from_iter(entries.iter().map(|(k, v)| (k.to_string(), v.to_json())));
+
+        return serde_json::Value::from(map);
+    }
+}
+
+impl From<bool> for TemplateValue<'_> {
+    fn from(b: bool) -> Self {
+        return TemplateValue::Bool(b);
+    }
+}
+
+impl From<i32> for TemplateValue<'_> {
+    fn from(n: i32) -> Self {
+        return TemplateValue::Integer(n.into());
+    }
+}
+
+impl From<i64> for TemplateValue<'_> {
+    fn from(n: i64) -> Self {
+        return TemplateValue::Integer(n);
+    }
+}
+
+impl From<f64> for TemplateValue<'_> {
+    fn from(n: f64) -> Self {
+        return TemplateValue::Float(n);
+    }
+}
+
+impl<'a> From<&'a str> for TemplateValue<'a> {
+    fn from(s: &'a str) -> Self {
+        return TemplateValue::String(Cow::Borrowed(s));
+    }
+}
+
+impl From<String> for TemplateValue<'_> {
+    fn from(s: String) -> Self {
+        return TemplateValue::String(Cow::Owned(s));
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for TemplateValue<'a> {
+    fn from(s: Cow<'a, str>) -> Self {
+        return TemplateValue::String(s);
+    }
+}
+
+impl<'a, T: Into<TemplateValue<'a>>> From<Vec<T>> for TemplateValue<'a> {
+    fn from(items: Vec<T>) -> Self {
+        return TemplateValue::List(items.into_iter().map(Into::into).collect());
+    }
+}
+
+impl<'a, T: Into<TemplateValue<'a>>> From<Option<T>> for TemplateValue<'a> {
+    fn from(value: Option<T>) -> Self {
+        return value.map_or(TemplateValue::Null, Into::into);
+    }
+}
 
 impl Message<'_> {
     pub fn builder<'a>() -> MessageBuilder<'a> {
         return MessageBuilder::new();
     }
+
+    pub fn into_owned(self) -> Message<'static> {
+        return Message {
+            category: self.category.map(into_owned_str),
+            metadata: into_owned_pairs(self.metadata),
+            from: self.from.into_owned(),
+            reply_to: self.reply_to.map(Address::into_owned),
+            to: self.to.into_iter().map(Address::into_owned).collect(),
+            cc: self.cc.into_iter().map(Address::into_owned).collect(),
+            bcc: self.bcc.into_iter().map(Address::into_owned).collect(),
+            message_id: self.message_id.map(into_owned_str),
+            date: self.date,
+            custom_message_id: self.custom_message_id,
+            headers: into_owned_pairs(self.headers),
+            subject: into_owned_str(self.subject),
+            text_body: self.text_body.map(into_owned_str),
+            html_body: self.html_body.map(into_owned_str),
+            attachments: self
+                .attachments
+                .into_iter()
+                .map(MessageAttachment::into_owned)
+                .collect(),
+            template: self.template.map(MessageTemplate::into_owned),
+        };
+    }
+
+    /// The `Message-ID` and `Date` headers (when given), followed by the custom headers.
+    #[cfg(any(
+        feature = "aws_ses",
+        feature = "azure_email",
+        feature = "brevo",
+        feature = "mailersend",
+        feature = "mailgun",
+        feature = "mailjet",
+        feature = "mailtrap",
+        feature = "mandrill",
+        feature = "postmark",
+        feature = "resend",
+        feature = "sendgrid",
+        feature = "sparkpost",
+        feature = "webhook"
+    ))]
+    pub(crate) fn all_headers(&self) -> Vec<(Cow<'_, str>, Cow<'_, str>)> {
+        let mut headers = Vec::with_capacity(self.headers.len() + 2);
+
+        if let Some(message_id) = self.message_id.as_ref().filter(|_| self.custom_message_id) {
+            headers.push((
+                Cow::Borrowed("Message-ID"),
+                Cow::Borrowed(message_id.as_ref()),
+            ));
+        }
+
+        if let Some(date) = self.date.filter(|_| self.custom_message_id) {
+            headers.push((
+                Cow::Borrowed("Date"),
+                Cow::Owned(utils::format_email_date(date)),
+            ));
+        }
+
+        for (k, v) in &self.headers {
+            headers.push((Cow::Borrowed(k.as_ref()), Cow::Borrowed(v.as_ref())));
+        }
+
+        return headers;
+    }
+
+    /// Inlines the CSS of the HTML body, e.g. for messages not made with the builder.
+    #[cfg(feature = "css_inline")]
+    pub fn inline_css(
+        &mut self,
+        options: html::InlineCssOptions,
+    ) -> Result<(), css_inline::InlineError> {
+        if let Some(html) = &self.html_body {
+            self.html_body = Some(Cow::Owned(html::inline_css(html, options)?));
+        }
+
+        return Ok(());
+    }
+}
+
+fn into_owned_str(s: Cow<'_, str>) -> Cow<'static, str> {
+    return Cow::Owned(s.into_owned());
+}
+
+fn into_owned_pairs(
+    pairs: Vec<(Cow<'_, str>, Cow<'_, str>)>,
+) -> Vec<(Cow<'static, str>, Cow<'static, str>)> {
+    return pairs
+        .into_iter()
+        .map(|(k, v)| (into_owned_str(k), into_owned_str(v)))
+        .collect();
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -40,6 +335,8 @@ pub enum MessageBuilderError {
     MissingTo,
     MissingSubject,
     MissingBody,
+    #[cfg(feature = "css_inline")]
+    InlineCss(css_inline::InlineError),
 }
 
 impl fmt::Display for MessageBuilderError {
@@ -51,6 +348,10 @@ impl fmt::Display for MessageBuilderError {
             MessageBuilderError::MissingBody => {
                 "Body is missing, provide at least one of `text_body` or `html_body`"
             }
+            #[cfg(feature = "css_inline")]
+            MessageBuilderError::InlineCss(error) => {
+                return write!(f, "Failed to inline the CSS: {error}");
+            }
         };
 
         return write!(f, "{message}");
@@ -68,10 +369,18 @@ pub struct MessageBuilder<'a> {
     to: Vec<Address<'a>>,
     cc: Vec<Address<'a>>,
     bcc: Vec<Address<'a>>,
+    message_id: Option<Cow<'a, str>>,
+    message_id_domain: Option<Cow<'a, str>>,
+    date: Option<SystemTime>,
     headers: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     subject: Option<Cow<'a, str>>,
     text_body: Option<Cow<'a, str>>,
     html_body: Option<Cow<'a, str>>,
+    attachments: Vec<MessageAttachment<'a>>,
+    template: Option<MessageTemplate<'a>>,
+    auto_text_body: bool,
+    #[cfg(feature = "css_inline")]
+    inline_css: Option<html::InlineCssOptions>,
 }
 
 impl<'a> MessageBuilder<'a> {
@@ -181,6 +490,27 @@ impl<'a> MessageBuilder<'a> {
         return self;
     }
 
+    /// Uses the given `Message-ID` (e.g. `<id@example.com>`), instead of generating one.
+    pub fn message_id(mut self, message_id: impl Into<Cow<'a, str>>) -> Self {
+        self.message_id = Some(message_id.into());
+
+        return self;
+    }
+
+    /// The domain of the generated `Message-ID`, defaults to the domain of the sender.
+    pub fn message_id_domain(mut self, domain: impl Into<Cow<'a, str>>) -> Self {
+        self.message_id_domain = Some(domain.into());
+
+        return self;
+    }
+
+    /// Uses the given `Date`, instead of the time when the message is built.
+    pub fn date(mut self, date: SystemTime) -> Self {
+        self.date = Some(date);
+
+        return self;
+    }
+
     pub fn headers(mut self, key: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
         self.headers.push((key.into(), value.into()));
 
@@ -219,6 +549,13 @@ impl<'a> MessageBuilder<'a> {
         return self;
     }
 
+    /// Derives the text body from the HTML body when it's not given, see `html::to_text`.
+    pub fn auto_text_body(mut self) -> Self {
+        self.auto_text_body = true;
+
+        return self;
+    }
+
     pub fn html_body(mut self, body: impl Into<Cow<'a, str>>) -> Self {
         self.html_body = Some(body.into());
 
@@ -231,6 +568,39 @@ impl<'a> MessageBuilder<'a> {
         return self;
     }
 
+    /// Inlines the CSS of the HTML body when building, see `html::inline_css`.
+    #[cfg(feature = "css_inline")]
+    pub fn inline_css(mut self, options: html::InlineCssOptions) -> Self {
+        self.inline_css = Some(options);
+
+        return self;
+    }
+
+    pub fn attachment(mut self, attachment: MessageAttachment<'a>) -> Self {
+        self.attachments.push(attachment);
+
+        return self;
+    }
+
+    pub fn set_attachments(mut self, attachments: Vec<MessageAttachment<'a>>) -> Self {
+        self.attachments = attachments;
+
+        return self;
+    }
+
+    /// Uses a provider-hosted template, which makes the subject and body optional.
+    pub fn template(mut self, template: MessageTemplate<'a>) -> Self {
+        self.template = Some(template);
+
+        return self;
+    }
+
+    pub fn set_template(mut self, template: Option<MessageTemplate<'a>>) -> Self {
+        self.template = template;
+
+        return self;
+    }
+
     pub fn build(self) -> Result<Message<'a>, MessageBuilderError> {
         let from = self.from.ok_or(MessageBuilderError::MissingFrom)?;
 
@@ -238,12 +608,49 @@ impl<'a> MessageBuilder<'a> {
             return Err(MessageBuilderError::MissingTo);
         }
 
-        let subject = self.subject.ok_or(MessageBuilderError::MissingSubject)?;
+        // The template provides the subject and body when they're not given
+        let has_template = self.template.is_some();
+
+        let subject = match self.subject {
+            Some(subject) => subject,
+            None if has_template => Cow::Borrowed(""),
+            None => return Err(MessageBuilderError::MissingSubject),
+        };
 
-        if self.text_body.is_none() && self.html_body.is_none() {
+        if !has_template && self.text_body.is_none() && self.html_body.is_none() {
             return Err(MessageBuilderError::MissingBody);
         }
 
+        let html_body = self.html_body;
+
+        #[cfg(feature = "css_inline")]
+        let html_body = match (html_body, self.inline_css) {
+            (Some(html), Some(options)) => Some(Cow::Owned(
+                html::inline_css(&html, options).map_err(MessageBuilderError::InlineCss)?,
+            )),
+            (html_body, _) => html_body,
+        };
+
+        let text_body = match (self.text_body, &html_body) {
+            (None, Some(html)) if self.auto_text_body => Some(Cow::Owned(html::to_text(html))),
+            (text_body, _) => text_body,
+        };
+
+        let custom_message_id = self.message_id.is_some() || self.date.is_some();
+
+        // Generated once here, so that retries and signed copies keep the same ID
+        let message_id = match self.message_id {
+            Some(message_id) => message_id,
+            None => {
+                let domain = match &self.message_id_domain {
+                    Some(domain) => domain.as_ref(),
+                    None => utils::email_domain(&from.email),
+                };
+
+                Cow::Owned(utils::generate_message_id(domain))
+            }
+        };
+
         return Ok(Message {
             category: self.category,
             metadata: self.metadata,
@@ -252,10 +659,15 @@ impl<'a> MessageBuilder<'a> {
             to: self.to,
             cc: self.cc,
             bcc: self.bcc,
+            message_id: Some(message_id),
+            date: Some(self.date.unwrap_or_else(SystemTime::now)),
+            custom_message_id,
             headers: self.headers,
             subject,
-            text_body: self.text_body,
-            html_body: self.html_body,
+            text_body,
+            html_body,
+            attachments: self.attachments,
+            template: self.template,
         });
     }
 }
@@ -282,4 +694,100 @@ mod tests {
 
         // TODO: Test the optional fields
     }
+
+    #[test]
+    fn test_message_builder_template() {
+        let result = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .build();
+        assert!(matches!(result, Err(MessageBuilderError::MissingSubject)));
+
+        let template = MessageTemplate::new("welcome")
+            .variable("name", "Recipient")
+            .variable("items", vec!["a", "b"]);
+
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .template(template.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(message.subject, "");
+        assert_eq!(message.text_body, None);
+        assert_eq!(message.template, Some(template));
+    }
+
+    #[test]
+    fn test_message_builder_auto_text_body() {
+        let builder = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .html_body("<p>This is a <a href=\"https://example.com\">test</a> email.</p>")
+            .auto_text_body();
+
+        let message = builder.clone().build().unwrap();
+        assert_eq!(
+            message.text_body.as_deref(),
+            Some("This is a test [1] email.\n\n[1] https://example.com")
+        );
+
+        let message = builder.text_body("Custom text").build().unwrap();
+        assert_eq!(message.text_body.as_deref(), Some("Custom text"));
+    }
+
+    #[test]
+    fn test_message_builder_message_id() {
+        let builder = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.");
+
+        let message = builder.clone().build().unwrap();
+        assert!(message.message_id.unwrap().ends_with("@example.com>"));
+        assert!(message.date.is_some());
+        assert!(!message.custom_message_id);
+
+        let message = builder
+            .clone()
+            .message_id_domain("mail.example.com")
+            .build()
+            .unwrap();
+        assert!(message.message_id.unwrap().ends_with("@mail.example.com>"));
+
+        let date = SystemTime::UNIX_EPOCH;
+        let message = builder
+            .message_id("<abc@example.com>")
+            .date(date)
+            .build()
+            .unwrap();
+        assert_eq!(message.message_id.as_deref(), Some("<abc@example.com>"));
+        assert_eq!(message.date, Some(date));
+        assert!(message.custom_message_id);
+    }
+
+    #[cfg(feature = "css_inline")]
+    #[test]
+    fn test_message_builder_inline_css() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .html_body("<style>p { margin: 0; }</style><p>This is a test email.</p>")
+            .inline_css(html::InlineCssOptions::default())
+            .auto_text_body()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            message.html_body.as_deref(),
+            Some(
+                "<html><head></head><body><p style=\"margin: 0;\">This is a test email.</p></body></html>"
+            )
+        );
+        assert_eq!(message.text_body.as_deref(), Some("This is a test email."));
+    }
 }
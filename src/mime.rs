@@ -0,0 +1,421 @@
+use std::fmt::Write as _;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+
+use base64::Engine as _;
+use base64::prelude::BASE64_STANDARD;
+
+use crate::Address;
+use crate::Message;
+use crate::MessageAttachment;
+use crate::utils;
+use crate::utils::encode_mime_b;
+
+// Recommended max line length, excluding the CRLF (RFC 5322, section 2.1.1)
+const MAX_LINE_LENGTH: usize = 78;
+
+// Max bytes per encoded-word, so that it stays under 75 chars (RFC 2047, section 2)
+const MAX_ENCODED_WORD_BYTES: usize = 45;
+
+/// Renders the message as an RFC 5322 / MIME email with CRLF line endings.
+///
+/// The `Bcc` recipients are left out of the headers,
+/// they should be passed to the transport as envelope recipients instead.
+pub fn render_message(m: &Message) -> String {
+    return render_message_with_boundary(m, &generate_boundary());
+}
+
+pub(crate) fn render_message_with_boundary(m: &Message, boundary: &str) -> String {
+    let mut out = String::new();
+
+    write_header(&mut out, "From", &m.from.to_string());
+
+    if let Some(reply_to) = &m.reply_to {
+        write_header(&mut out, "Reply-To", &reply_to.to_string());
+    }
+
+    write_address_header(&mut out, "To", &m.to);
+
+    if !m.cc.is_empty() {
+        write_address_header(&mut out, "Cc", &m.cc);
+    }
+
+    write_header(&mut out, "Subject", &encode_header_text(&m.subject));
+
+    // Both are required (RFC 5322, section 3.6), and only missing when the message wasn't built
+    match &m.message_id {
+        Some(message_id) => write_header(&mut out, "Message-ID", message_id),
+        None => {
+            let domain = utils::email_domain(&m.from.email);
+            write_header(&mut out, "Message-ID", &utils::generate_message_id(domain));
+        }
+    }
+
+    let date = m.date.unwrap_or_else(SystemTime::now);
+    write_header(&mut out, "Date", &utils::format_email_date(date));
+
+    for (k, v) in &m.headers {
+        write_header(&mut out, k, &encode_header_text(v));
+    }
+
+    write_header(&mut out, "MIME-Version", "1.0");
+
+    if m.attachments.is_empty() {
+        write_body(&mut out, m, boundary);
+    } else {
+        let mixed_boundary = format!("{boundary}-mixed");
+        write_multipart_start(&mut out, "multipart/mixed", &mixed_boundary);
+
+        write_boundary(&mut out, &mixed_boundary);
+        write_body(&mut out, m, boundary);
+
+        for attachment in &m.attachments {
+            write_boundary(&mut out, &mixed_boundary);
+            write_attachment(&mut out, attachment);
+        }
+
+        write_multipart_end(&mut out, &mixed_boundary);
+    }
+
+    return out;
+}
+
+fn write_body(out: &mut String, m: &Message, boundary: &str) {
+    match (&m.text_body, &m.html_body) {
+        (Some(text), Some(html)) => {
+            let alt_boundary = format!("{boundary}-alt");
+            write_multipart_start(out, "multipart/alternative", &alt_boundary);
+
+            write_boundary(out, &alt_boundary);
+            write_text_part(out, "text/plain", text);
+
+            write_boundary(out, &alt_boundary);
+            write_text_part(out, "text/html", html);
+
+            write_multipart_end(out, &alt_boundary);
+        }
+        (None, Some(html)) => write_text_part(out, "text/html", html),
+        (Some(text), None) => write_text_part(out, "text/plain", text),
+        (None, None) => write_text_part(out, "text/plain", ""),
+    }
+}
+
+fn write_text_part(out: &mut String, content_type: &str, text: &str) {
+    write_header(
+        out,
+        "Content-Type",
+        &format!("{content_type}; charset=UTF-8"),
+    );
+    write_header(out, "Content-Transfer-Encoding", "base64");
+    out.push_str("\r\n");
+
+    // Text must use CRLF line endings in its canonical form (RFC 2045, section 6.8)
+    let text = text.replace("\r\n", "\n").replace('\n', "\r\n");
+    write_base64(out, text.as_bytes());
+}
+
+fn write_attachment(out: &mut String, attachment: &MessageAttachment) {
+    let name = encode_param("name", &attachment.name);
+    let filename = encode_param("filename", &attachment.name);
+
+    write_header(
+        out,
+        "Content-Type",
+        &format!("{}; {name}", attachment.content_type),
+    );
+    write_header(
+        out,
+        "Content-Disposition",
+        &format!("attachment; {filename}"),
+    );
+    write_header(out, "Content-Transfer-Encoding", "base64");
+    out.push_str("\r\n");
+
+    write_base64(out, &attachment.bytes);
+}
+
+pub(crate) fn write_multipart_start(out: &mut String, content_type: &str, boundary: &str) {
+    write_header(
+        out,
+        "Content-Type",
+        &format!("{content_type}; boundary=\"{boundary}\""),
+    );
+    out.push_str("\r\n");
+}
+
+pub(crate) fn write_boundary(out: &mut String, boundary: &str) {
+    write!(out, "--{boundary}\r\n").unwrap();
+}
+
+pub(crate) fn write_multipart_end(out: &mut String, boundary: &str) {
+    write!(out, "--{boundary}--\r\n").unwrap();
+}
+
+pub(crate) fn write_header(out: &mut String, name: &str, value: &str) {
+    write!(out, "{name}: {value}\r\n").unwrap();
+}
+
+pub(crate) fn write_address_header(out: &mut String, name: &str, addrs: &[Address]) {
+    let mut line_length = name.len() + 2;
+    let mut value = String::new();
+
+    for (i, addr) in addrs.iter().enumerate() {
+        let addr = addr.to_string();
+
+        if i > 0 {
+            value.push(',');
+            line_length += 1;
+
+            if line_length + 1 + addr.len() > MAX_LINE_LENGTH {
+                value.push_str("\r\n");
+                line_length = 0;
+            }
+
+            value.push(' ');
+            line_length += 1;
+        }
+
+        value.push_str(&addr);
+        line_length += addr.len();
+    }
+
+    write_header(out, name, &value);
+}
+
+pub(crate) fn write_base64(out: &mut String, bytes: &[u8]) {
+    let encoded = BASE64_STANDARD.encode(bytes);
+
+    // Base64 is pure ASCII, so slicing by bytes is safe
+    for chunk in encoded.as_bytes().chunks(76) {
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\r\n");
+    }
+}
+
+// Encodes the header value as one or more encoded-words if needed (RFC 2047)
+fn encode_header_text(s: &str) -> String {
+    if s.bytes().all(|b| b == b'\t' || (b' ' <= b && b <= b'~')) {
+        return s.to_string();
+    }
+
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        if i + c.len_utf8() - start > MAX_ENCODED_WORD_BYTES {
+            words.push(encode_mime_b(&s[start..i]));
+            start = i;
+        }
+    }
+    words.push(encode_mime_b(&s[start..]));
+
+    return words.join("\r\n ");
+}
+
+// Encodes a parameter, using RFC 2231 for non-ASCII values
+fn encode_param(key: &str, value: &str) -> String {
+    if value.bytes().all(|b| b' ' <= b && b <= b'~') {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+
+        return format!("{key}=\"{value}\"");
+    }
+
+    let mut encoded = String::with_capacity(value.len() * 3);
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            write!(encoded, "%{b:02X}").unwrap();
+        }
+    }
+
+    return format!("{key}*=UTF-8''{encoded}");
+}
+
+// Splits the message into its header fields (including the folded lines and CRLF) and body
+#[cfg(any(
+    feature = "dkim",
+    feature = "gmail",
+    feature = "pgp",
+    feature = "smime"
+))]
+pub(crate) fn split_message(raw: &str) -> (Vec<&str>, &str) {
+    let (header_block, body) = match raw.find("\r\n\r\n") {
+        Some(i) => (&raw[..i + 2], &raw[i + 4..]),
+        None => (raw, ""),
+    };
+
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+
+    for line in header_block.split_inclusive("\r\n") {
+        if pos > 0 && !line.starts_with([' ', '\t']) {
+            fields.push(&header_block[start..pos]);
+            start = pos;
+        }
+
+        pos += line.len();
+    }
+
+    if pos > start {
+        fields.push(&header_block[start..pos]);
+    }
+
+    return (fields, body);
+}
+
+// Splits the message into the message headers, and the entity to sign or encrypt,
+// i.e. the `Content-*` headers and the body
+#[cfg(any(feature = "pgp", feature = "smime"))]
+pub(crate) fn split_entity(raw: &str) -> (String, String) {
+    let (fields, body) = split_message(raw);
+    let mut headers = String::new();
+    let mut entity = String::new();
+
+    for field in fields {
+        let is_content_field = field
+            .get(..8)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("content-"));
+
+        if is_content_field {
+            entity.push_str(field);
+        } else {
+            headers.push_str(field);
+        }
+    }
+
+    entity.push_str("\r\n");
+    entity.push_str(body);
+
+    return (headers, entity);
+}
+
+pub(crate) fn generate_boundary() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    // "=_" can never appear in base64 encoded content
+    return format!("=_{nanos:x}.{count:x}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_message() {
+        let message = Message::builder()
+            .from(("Sender", "sender@example.com"))
+            .to(("Recipient", "recipient@example.com"))
+            .bcc("bcc@example.com")
+            .headers("X-Test", "yes")
+            .subject("Test Email")
+            .text_body("Hello,\nworld!")
+            .html_body("<p>Hello, world!</p>")
+            .attachment(MessageAttachment::new(
+                "résumé.txt",
+                "text/plain",
+                b"Hi!".as_slice(),
+            ))
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+
+        let expected = [
+            "From: \"Sender\" <sender@example.com>",
+            "To: \"Recipient\" <recipient@example.com>",
+            "Subject: Test Email",
+            "Message-ID: <abc123@example.com>",
+            "Date: Thu, 01 Jan 1970 00:00:00 +0000",
+            "X-Test: yes",
+            "MIME-Version: 1.0",
+            "Content-Type: multipart/mixed; boundary=\"b-mixed\"",
+            "",
+            "--b-mixed",
+            "Content-Type: multipart/alternative; boundary=\"b-alt\"",
+            "",
+            "--b-alt",
+            "Content-Type: text/plain; charset=UTF-8",
+            "Content-Transfer-Encoding: base64",
+            "",
+            "SGVsbG8sDQp3b3JsZCE=",
+            "--b-alt",
+            "Content-Type: text/html; charset=UTF-8",
+            "Content-Transfer-Encoding: base64",
+            "",
+            "PHA+SGVsbG8sIHdvcmxkITwvcD4=",
+            "--b-alt--",
+            "--b-mixed",
+            "Content-Type: text/plain; name*=UTF-8''r%C3%A9sum%C3%A9.txt",
+            "Content-Disposition: attachment; filename*=UTF-8''r%C3%A9sum%C3%A9.txt",
+            "Content-Transfer-Encoding: base64",
+            "",
+            "SGkh",
+            "--b-mixed--",
+            "",
+        ]
+        .join("\r\n");
+
+        let actual = render_message_with_boundary(&message, "b");
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_render_message_generates_headers() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("Hello, world!")
+            .build()
+            .unwrap();
+
+        let raw = render_message(&message);
+        let (_, rest) = raw
+            .split_once("Subject: Test Email\r\nMessage-ID: <")
+            .unwrap();
+        let (message_id, rest) = rest.split_once(">\r\n").unwrap();
+
+        assert!(message_id.ends_with("@example.com"));
+        assert!(rest.starts_with("Date: "));
+    }
+
+    #[test]
+    fn test_encode_header_text() {
+        assert_eq!(encode_header_text("Hello"), "Hello");
+        assert_eq!(encode_header_text("¡Hola!"), "=?UTF-8?B?wqFIb2xhIQ==?=");
+
+        let encoded = encode_header_text(&"é".repeat(30));
+        let words: Vec<_> = encoded.split("\r\n ").collect();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|w| w.len() <= 75));
+    }
+
+    #[test]
+    fn test_write_address_header() {
+        let addrs: Vec<_> = (0..4)
+            .map(|i| Address::new(format!("recipient{i}@example.com")))
+            .collect();
+
+        let mut actual = String::new();
+        write_address_header(&mut actual, "To", &addrs);
+
+        let expected = [
+            "To: recipient0@example.com, recipient1@example.com, recipient2@example.com,",
+            " recipient3@example.com",
+            "",
+        ]
+        .join("\r\n");
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use tokio::sync::MutexGuard;
+use tokio::sync::OnceCell;
+
+use super::OutboxEntry;
+use super::OutboxStatus;
+use super::OutboxStore;
+use super::is_sent_before;
+
+// When each unfinished entry is due, by ID
+type Queue = HashMap<String, SystemTime>;
+
+/// Persists each entry as a JSON file (`{id}.json`) in a directory.
+///
+/// Writes go to a temporary file first and are then renamed into place,
+/// so an entry is never left half-written if the process dies mid-write.
+///
+/// Files that can't be parsed (e.g. corrupted or edited by hand) are renamed to
+/// `{id}.json.corrupt` and logged, so that they don't hold up the rest of the queue.
+///
+/// When the unfinished entries are due is kept in memory, so that polling only reads
+/// the due ones. The clones of a store share it, but other stores don't, so a directory
+/// should only be used by one store (and its clones) at a time.
+#[derive(Debug, Clone)]
+pub struct FileOutboxStore {
+    dir: PathBuf,
+    // Loaded from the files on first use, its lock also keeps claims from overlapping
+    queue: Arc<OnceCell<Mutex<Queue>>>,
+}
+
+impl FileOutboxStore {
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        return Ok(Self {
+            dir,
+            queue: Arc::new(OnceCell::new()),
+        });
+    }
+
+    async fn queue(&self) -> io::Result<MutexGuard<'_, Queue>> {
+        let queue = self
+            .queue
+            .get_or_try_init(|| async {
+                let mut queue = Queue::new();
+                for entry in self.read_all().await? {
+                    track(&mut queue, &entry);
+                }
+
+                return Ok::<_, io::Error>(Mutex::new(queue));
+            })
+            .await?;
+
+        return Ok(queue.lock().await);
+    }
+
+    fn entry_path(&self, id: &str) -> io::Result<PathBuf> {
+        // IDs end up in file names, so keep them to a safe set of chars
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if id.is_empty() || !id.chars().all(is_safe) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid outbox entry ID: {id}"),
+            ));
+        }
+
+        return Ok(self.dir.join(format!("{id}.json")));
+    }
+
+    async fn write(&self, path: &Path, entry: &OutboxEntry) -> io::Result<()> {
+        let json = serde_json::to_vec(entry).map_err(io::Error::other)?;
+
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, json).await?;
+        tokio::fs::rename(&tmp_path, path).await?;
+
+        return Ok(());
+    }
+
+    async fn read(&self, path: &Path) -> io::Result<OutboxEntry> {
+        let json = tokio::fs::read(path).await?;
+
+        return serde_json::from_slice(&json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+
+    async fn quarantine(&self, path: &Path, err: io::Error) -> io::Result<()> {
+        let corrupt_path = path.with_extension("json.corrupt");
+        log::error!(
+            "Unreadable outbox entry {}, moving it to {}: {err}",
+            path.display(),
+            corrupt_path.display(),
+        );
+
+        return tokio::fs::rename(path, corrupt_path).await;
+    }
+
+    async fn read_all(&self) -> io::Result<Vec<OutboxEntry>> {
+        let mut entries = Vec::new();
+
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(dir_entry) = dir.next_entry().await? {
+            let path = dir_entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            if let Some(entry) = self.read_or_quarantine(&path).await? {
+                entries.push(entry);
+            }
+        }
+
+        return Ok(entries);
+    }
+
+    async fn read_or_quarantine(&self, path: &Path) -> io::Result<Option<OutboxEntry>> {
+        return match self.read(path).await {
+            Ok(entry) => Ok(Some(entry)),
+            // Removed since it was listed, e.g. by a concurrent `purge_sent`
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            // Only unparsable files are set aside, other errors (e.g. permissions) may not last
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                self.quarantine(path, err).await?;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        };
+    }
+}
+
+fn track(queue: &mut Queue, entry: &OutboxEntry) {
+    match entry.due_at() {
+        Some(due_at) => queue.insert(entry.id.clone(), due_at),
+        None => queue.remove(&entry.id),
+    };
+}
+
+#[async_trait]
+impl OutboxStore for FileOutboxStore {
+    async fn insert(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let path = self.entry_path(&entry.id)?;
+        let mut queue = self.queue().await?;
+
+        if tokio::fs::try_exists(&path).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Outbox entry already exists: {}", entry.id),
+            ));
+        }
+
+        self.write(&path, entry).await?;
+        track(&mut queue, entry);
+
+        return Ok(());
+    }
+
+    async fn update(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let path = self.entry_path(&entry.id)?;
+        let mut queue = self.queue().await?;
+
+        if !tokio::fs::try_exists(&path).await? {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Outbox entry not found: {}", entry.id),
+            ));
+        }
+
+        self.write(&path, entry).await?;
+        track(&mut queue, entry);
+
+        return Ok(());
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Option<OutboxEntry>> {
+        let path = self.entry_path(id)?;
+
+        return match self.read(&path).await {
+            Ok(entry) => Ok(Some(entry)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        };
+    }
+
+    async fn claim(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: usize,
+    ) -> io::Result<Vec<OutboxEntry>> {
+        let mut queue = self.queue().await?;
+
+        let mut due: Vec<_> = queue
+            .iter()
+            .filter(|(_, due_at)| **due_at <= now)
+            .map(|(id, due_at)| (*due_at, id.clone()))
+            .collect();
+        due.sort();
+        due.truncate(limit);
+
+        let mut claimed = Vec::with_capacity(due.len());
+
+        for (_, id) in due {
+            let path = self.entry_path(&id)?;
+
+            let Some(mut entry) = self.read_or_quarantine(&path).await? else {
+                queue.remove(&id);
+                continue;
+            };
+
+            entry.claim(now, lease);
+            self.write(&path, &entry).await?;
+            track(&mut queue, &entry);
+
+            claimed.push(entry);
+        }
+
+        return Ok(claimed);
+    }
+
+    async fn dead_letters(&self) -> io::Result<Vec<OutboxEntry>> {
+        return Ok(self
+            .read_all()
+            .await?
+            .into_iter()
+            .filter(|e| e.status == OutboxStatus::DeadLettered)
+            .collect());
+    }
+
+    async fn purge_sent(&self, sent_before: SystemTime) -> io::Result<usize> {
+        // Keeps the entries from being updated while they're removed
+        let _queue = self.queue().await?;
+
+        let mut count = 0;
+
+        for entry in self.read_all().await? {
+            if is_sent_before(&entry, sent_before) {
+                tokio::fs::remove_file(self.entry_path(&entry.id)?).await?;
+                count += 1;
+            }
+        }
+
+        return Ok(count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message;
+
+    #[tokio::test]
+    async fn test_file_outbox_store() {
+        let dir = std::env::temp_dir().join(format!("gen_mailer-{}", uuid::Uuid::new_v4()));
+        let store = FileOutboxStore::open(&dir).unwrap();
+
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to(("Recipient", "recipient@example.com"))
+            .subject("Test Email")
+            .html_body("<p>This is a test email.</p>")
+            .build()
+            .unwrap();
+
+        let mut entry = OutboxEntry::new(message.into_owned());
+        store.insert(&entry).await.unwrap();
+        assert!(store.insert(&entry).await.is_err());
+
+        let now = SystemTime::now();
+        let lease = Duration::from_secs(60);
+        let due = store.claim(now, lease, 10).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, entry.id);
+        assert_eq!(due[0].message.to[0].name.as_deref(), Some("Recipient"));
+        assert_eq!(due[0].message.subject, "Test Email");
+
+        // Claimed until the lease runs out, also for the clones of the store
+        assert!(
+            store
+                .clone()
+                .claim(now, lease, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let stored = store.get(&entry.id).await.unwrap().unwrap();
+        assert_eq!(
+            stored.status,
+            OutboxStatus::Sending {
+                lease_until: now + lease,
+            },
+        );
+        let due = store.claim(now + lease, lease, 10).await.unwrap();
+        assert_eq!(due.len(), 1);
+
+        entry.status = OutboxStatus::DeadLettered;
+        store.update(&entry).await.unwrap();
+
+        let reopened = FileOutboxStore::open(&dir).unwrap();
+        let stored = reopened.get(&entry.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OutboxStatus::DeadLettered);
+        assert_eq!(reopened.dead_letters().await.unwrap().len(), 1);
+        assert!(
+            reopened
+                .claim(SystemTime::now() + lease * 2, lease, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(reopened.get("../escape").await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_outbox_store_corrupt_entry() {
+        let dir = std::env::temp_dir().join(format!("gen_mailer-{}", uuid::Uuid::new_v4()));
+        let store = FileOutboxStore::open(&dir).unwrap();
+
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let entry = OutboxEntry::new(message.into_owned());
+        store.insert(&entry).await.unwrap();
+        std::fs::write(dir.join("corrupt.json"), "{ \"id\": ").unwrap();
+
+        // The files are read when a store first needs to know what is due
+        let reopened = FileOutboxStore::open(&dir).unwrap();
+        let due = reopened
+            .claim(SystemTime::now(), Duration::from_secs(60), 10)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, entry.id);
+
+        assert!(!dir.join("corrupt.json").exists());
+        assert!(dir.join("corrupt.json.corrupt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_outbox_store_unreadable_entry() {
+        let dir = std::env::temp_dir().join(format!("gen_mailer-{}", uuid::Uuid::new_v4()));
+        let store = FileOutboxStore::open(&dir).unwrap();
+
+        // Not a parsing error, so it may go away and must not be quarantined
+        std::fs::create_dir(dir.join("unreadable.json")).unwrap();
+
+        assert!(store.dead_letters().await.is_err());
+        assert!(dir.join("unreadable.json").exists());
+        assert!(!dir.join("unreadable.json.corrupt").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_file_outbox_store_purge_sent() {
+        let dir = std::env::temp_dir().join(format!("gen_mailer-{}", uuid::Uuid::new_v4()));
+        let store = FileOutboxStore::open(&dir).unwrap();
+
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let now = SystemTime::now();
+        let mut old = OutboxEntry::new(message.clone().into_owned());
+        old.status = OutboxStatus::Sent {
+            message_ids: Vec::new(),
+            sent_at: now - Duration::from_secs(60),
+        };
+        let mut recent = OutboxEntry::new(message.clone().into_owned());
+        recent.status = OutboxStatus::Sent {
+            message_ids: Vec::new(),
+            sent_at: now,
+        };
+        let pending = OutboxEntry::new(message.into_owned());
+
+        for entry in [&old, &recent, &pending] {
+            store.insert(entry).await.unwrap();
+        }
+
+        assert_eq!(store.purge_sent(now).await.unwrap(), 1);
+        assert!(store.get(&old.id).await.unwrap().is_none());
+        assert!(store.get(&recent.id).await.unwrap().is_some());
+        assert!(store.get(&pending.id).await.unwrap().is_some());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
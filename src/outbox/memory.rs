@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+
+use super::OutboxEntry;
+use super::OutboxStatus;
+use super::OutboxStore;
+use super::is_sent_before;
+
+/// Keeps entries in memory, they are lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryOutboxStore {
+    entries: Mutex<HashMap<String, OutboxEntry>>,
+}
+
+impl MemoryOutboxStore {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+}
+
+#[async_trait]
+impl OutboxStore for MemoryOutboxStore {
+    async fn insert(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.contains_key(&entry.id) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Outbox entry already exists: {}", entry.id),
+            ));
+        }
+
+        entries.insert(entry.id.clone(), entry.clone());
+
+        return Ok(());
+    }
+
+    async fn update(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let Some(existing) = entries.get_mut(&entry.id) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Outbox entry not found: {}", entry.id),
+            ));
+        };
+
+        *existing = entry.clone();
+
+        return Ok(());
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Option<OutboxEntry>> {
+        return Ok(self.entries.lock().unwrap().get(id).cloned());
+    }
+
+    async fn claim(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: usize,
+    ) -> io::Result<Vec<OutboxEntry>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let mut due: Vec<_> = entries.values_mut().filter(|e| e.is_due(now)).collect();
+        due.sort_by_key(|e| e.due_at());
+
+        let claimed = due
+            .into_iter()
+            .take(limit)
+            .map(|entry| {
+                entry.claim(now, lease);
+                return entry.clone();
+            })
+            .collect();
+
+        return Ok(claimed);
+    }
+
+    async fn dead_letters(&self) -> io::Result<Vec<OutboxEntry>> {
+        let entries = self.entries.lock().unwrap();
+
+        return Ok(entries
+            .values()
+            .filter(|e| e.status == OutboxStatus::DeadLettered)
+            .cloned()
+            .collect());
+    }
+
+    async fn purge_sent(&self, sent_before: SystemTime) -> io::Result<usize> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let count = entries.len();
+        entries.retain(|_, e| !is_sent_before(e, sent_before));
+
+        return Ok(count - entries.len());
+    }
+}
@@ -0,0 +1,274 @@
+//! A persistent outbox, which queues messages and sends them in the background with retries.
+//!
+//! Comes with a file store and an in-memory store, other backends (e.g. SQLite)
+//! can be added by implementing [`OutboxStore`].
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+
+mod file;
+pub use file::FileOutboxStore;
+
+mod memory;
+pub use memory::MemoryOutboxStore;
+
+mod worker;
+pub use worker::OutboxWorker;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub id: String,
+    pub message: Message<'static>,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub next_attempt_at: SystemTime,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OutboxStatus {
+    Pending,
+    /// Claimed by a worker, and due again if it isn't updated before the lease runs out
+    Sending {
+        lease_until: SystemTime,
+    },
+    Sent {
+        message_ids: Vec<String>,
+        sent_at: SystemTime,
+    },
+    DeadLettered,
+}
+
+impl OutboxEntry {
+    pub fn new(message: Message<'static>) -> Self {
+        return Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            message,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: SystemTime::now(),
+            last_error: None,
+        };
+    }
+
+    /// When the entry should be (re)sent, or `None` once it's sent or dead-lettered.
+    pub fn due_at(&self) -> Option<SystemTime> {
+        return match &self.status {
+            OutboxStatus::Pending => Some(self.next_attempt_at),
+            OutboxStatus::Sending { lease_until } => Some(*lease_until),
+            OutboxStatus::Sent { .. } | OutboxStatus::DeadLettered => None,
+        };
+    }
+
+    pub fn is_due(&self, now: SystemTime) -> bool {
+        return self.due_at().is_some_and(|due_at| due_at <= now);
+    }
+
+    // Marks the entry as taken by a worker until `now + lease`
+    fn claim(&mut self, now: SystemTime, lease: Duration) {
+        self.status = OutboxStatus::Sending {
+            lease_until: now + lease,
+        };
+    }
+}
+
+// NOTE: Several workers can share a store, `claim` hands each due entry
+// to only one of them, until its lease runs out.
+#[async_trait]
+pub trait OutboxStore: Send + Sync {
+    async fn insert(&self, entry: &OutboxEntry) -> io::Result<()>;
+
+    async fn update(&self, entry: &OutboxEntry) -> io::Result<()>;
+
+    async fn get(&self, id: &str) -> io::Result<Option<OutboxEntry>>;
+
+    /// Marks up to `limit` due entries as `Sending` until `now + lease` and returns them,
+    /// the ones that have been due the longest first.
+    async fn claim(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: usize,
+    ) -> io::Result<Vec<OutboxEntry>>;
+
+    async fn dead_letters(&self) -> io::Result<Vec<OutboxEntry>>;
+
+    /// Removes the entries sent before `sent_before`, returns the number removed.
+    async fn purge_sent(&self, sent_before: SystemTime) -> io::Result<usize>;
+}
+
+#[async_trait]
+impl<T: OutboxStore + ?Sized> OutboxStore for Arc<T> {
+    async fn insert(&self, entry: &OutboxEntry) -> io::Result<()> {
+        return (**self).insert(entry).await;
+    }
+
+    async fn update(&self, entry: &OutboxEntry) -> io::Result<()> {
+        return (**self).update(entry).await;
+    }
+
+    async fn get(&self, id: &str) -> io::Result<Option<OutboxEntry>> {
+        return (**self).get(id).await;
+    }
+
+    async fn claim(
+        &self,
+        now: SystemTime,
+        lease: Duration,
+        limit: usize,
+    ) -> io::Result<Vec<OutboxEntry>> {
+        return (**self).claim(now, lease, limit).await;
+    }
+
+    async fn dead_letters(&self) -> io::Result<Vec<OutboxEntry>> {
+        return (**self).dead_letters().await;
+    }
+
+    async fn purge_sent(&self, sent_before: SystemTime) -> io::Result<usize> {
+        return (**self).purge_sent(sent_before).await;
+    }
+}
+
+fn is_sent_before(entry: &OutboxEntry, time: SystemTime) -> bool {
+    return matches!(entry.status, OutboxStatus::Sent { sent_at, .. } if sent_at < time);
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        return Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(60 * 60),
+        };
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff: initial, 2x initial, 4x initial, ... up to max
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+
+        return self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+    }
+}
+
+/// A mailer that persists messages to an [`OutboxStore`] instead of sending them.
+/// The returned IDs are outbox entry IDs, pair it with an [`OutboxWorker`] to actually send.
+pub struct QueueMailer<S> {
+    store: S,
+}
+
+impl<S: OutboxStore> QueueMailer<S> {
+    pub fn new(store: S) -> Self {
+        return Self { store };
+    }
+
+    pub fn store(&self) -> &S {
+        return &self.store;
+    }
+
+    pub async fn status(&self, id: &str) -> io::Result<Option<OutboxStatus>> {
+        let entry = self.store.get(id).await?;
+
+        return Ok(entry.map(|e| e.status));
+    }
+
+    /// Moves a dead-lettered entry back into the queue, resetting its attempts.
+    pub async fn requeue(&self, id: &str) -> io::Result<bool> {
+        let Some(mut entry) = self.store.get(id).await? else {
+            return Ok(false);
+        };
+
+        if entry.status != OutboxStatus::DeadLettered {
+            return Ok(false);
+        }
+
+        entry.status = OutboxStatus::Pending;
+        entry.attempts = 0;
+        entry.next_attempt_at = SystemTime::now();
+        self.store.update(&entry).await?;
+
+        return Ok(true);
+    }
+
+    /// Removes the entries sent more than `retention` ago, returns the number removed.
+    pub async fn purge_sent(&self, retention: Duration) -> io::Result<usize> {
+        let sent_before = SystemTime::now()
+            .checked_sub(retention)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        return self.store.purge_sent(sent_before).await;
+    }
+}
+
+#[async_trait]
+impl<S: OutboxStore> GenericMailer for QueueMailer<S> {
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        let entry = OutboxEntry::new(m.clone().into_owned());
+        self.store.insert(&entry).await?;
+
+        return Ok(vec![entry.id]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(60),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(10));
+        assert_eq!(policy.backoff(2), Duration::from_secs(20));
+        assert_eq!(policy.backoff(3), Duration::from_secs(40));
+        assert_eq!(policy.backoff(4), Duration::from_secs(60));
+        assert_eq!(policy.backoff(100), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_queue_mailer() {
+        let message = Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+
+        let mailer = QueueMailer::new(MemoryOutboxStore::new());
+        let ids = mailer.send(&message).await.unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let status = mailer.status(&ids[0]).await.unwrap();
+        assert_eq!(status, Some(OutboxStatus::Pending));
+
+        let status = mailer.status("unknown").await.unwrap();
+        assert_eq!(status, None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::io;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use super::OutboxEntry;
+use super::OutboxStatus;
+use super::OutboxStore;
+use super::RetryPolicy;
+use crate::GenericMailer;
+
+/// Drains an [`OutboxStore`] through any [`GenericMailer`].
+///
+/// Failed sends are retried according to the [`RetryPolicy`],
+/// entries that run out of attempts are dead-lettered.
+///
+/// Entries are claimed for the `lease` before being sent, so several workers can share
+/// a store. An entry that isn't updated in time (e.g. the worker crashed, or the store
+/// failed after the send) is sent again once the lease runs out, so keep it well above
+/// the mailer's timeout.
+pub struct OutboxWorker<S, M> {
+    store: S,
+    mailer: M,
+    retry_policy: RetryPolicy,
+    batch_size: usize,
+    lease: Duration,
+}
+
+impl<S: OutboxStore, M: GenericMailer> OutboxWorker<S, M> {
+    pub fn new(store: S, mailer: M) -> Self {
+        return Self {
+            store,
+            mailer,
+            retry_policy: RetryPolicy::default(),
+            batch_size: 100,
+            lease: Duration::from_secs(5 * 60),
+        };
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+
+        return self;
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+
+        return self;
+    }
+
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+
+        return self;
+    }
+
+    /// Sends every entry that is currently due, returns the number of entries processed.
+    ///
+    /// When an entry can't be updated in the store, the rest of the batch is still processed,
+    /// and then the first error is returned.
+    pub async fn process_due(&self) -> io::Result<usize> {
+        let mut processed = 0;
+
+        loop {
+            let due = self
+                .store
+                .claim(SystemTime::now(), self.lease, self.batch_size)
+                .await?;
+            if due.is_empty() {
+                return Ok(processed);
+            }
+
+            let mut first_error = None;
+
+            for entry in due {
+                match self.process(entry).await {
+                    Ok(()) => processed += 1,
+                    Err(err) => {
+                        first_error.get_or_insert(err);
+                    }
+                }
+            }
+
+            // Stops instead of claiming more entries from a store that is failing
+            if let Some(err) = first_error {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Runs forever, checking for due entries every `poll_interval`.
+    ///
+    /// Store errors are logged and retried on the next poll, instead of stopping the worker.
+    pub async fn run(&self, poll_interval: Duration) {
+        loop {
+            if let Err(err) = self.process_due().await {
+                log::error!("Failed to process the outbox: {err}");
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn process(&self, mut entry: OutboxEntry) -> io::Result<()> {
+        entry.attempts += 1;
+
+        // NOTE: The error is turned into a string right away since it is not `Send`
+        let result = self
+            .mailer
+            .send(&entry.message)
+            .await
+            .map_err(|e| e.to_string());
+
+        match result {
+            Ok(message_ids) => {
+                entry.status = OutboxStatus::Sent {
+                    message_ids,
+                    sent_at: SystemTime::now(),
+                };
+                entry.last_error = None;
+            }
+            Err(err) => {
+                entry.last_error = Some(err);
+
+                if entry.attempts >= self.retry_policy.max_attempts {
+                    entry.status = OutboxStatus::DeadLettered;
+                } else {
+                    entry.status = OutboxStatus::Pending;
+                    entry.next_attempt_at =
+                        SystemTime::now() + self.retry_policy.backoff(entry.attempts);
+                }
+            }
+        }
+
+        return self.store.update(&entry).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::GenericMailerError;
+    use crate::Message;
+    use crate::outbox::MemoryOutboxStore;
+    use crate::outbox::QueueMailer;
+
+    struct FlakyMailer {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl GenericMailer for FlakyMailer {
+        async fn send(&self, _: &Message) -> Result<Vec<String>, GenericMailerError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                return Err(GenericMailerError::UnexpectedResponse(
+                    503,
+                    "Try again later".to_string(),
+                ));
+            }
+
+            return Ok(vec!["provider-id".to_string()]);
+        }
+    }
+
+    fn retry_policy(max_attempts: u32) -> RetryPolicy {
+        return RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        };
+    }
+
+    fn message() -> Message<'static> {
+        return Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("This is a test email.")
+            .build()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_outbox_worker_retries() {
+        let store = Arc::new(MemoryOutboxStore::new());
+        let queue = QueueMailer::new(store.clone());
+        let mailer = FlakyMailer {
+            failures: 2,
+            calls: AtomicU32::new(0),
+        };
+        let worker = OutboxWorker::new(store.clone(), mailer).retry_policy(retry_policy(3));
+
+        let id = queue.send(&message()).await.unwrap().remove(0);
+        assert_eq!(worker.process_due().await.unwrap(), 3);
+
+        let entry = store.get(&id).await.unwrap().unwrap();
+        assert_eq!(entry.attempts, 3);
+        assert_eq!(entry.last_error, None);
+
+        let OutboxStatus::Sent { message_ids, .. } = entry.status else {
+            panic!("Expected the entry to be sent, got: {:?}", entry.status);
+        };
+        assert_eq!(message_ids, ["provider-id"]);
+
+        assert_eq!(queue.purge_sent(Duration::from_secs(60)).await.unwrap(), 0);
+        assert_eq!(queue.purge_sent(Duration::ZERO).await.unwrap(), 1);
+        assert_eq!(queue.status(&id).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_worker_skips_claimed() {
+        let store = Arc::new(MemoryOutboxStore::new());
+        let queue = QueueMailer::new(store.clone());
+        let mailer = FlakyMailer {
+            failures: 0,
+            calls: AtomicU32::new(0),
+        };
+        let worker = OutboxWorker::new(store.clone(), mailer);
+
+        // e.g. by another worker, that is still sending it
+        queue.send(&message()).await.unwrap();
+        let lease = Duration::from_secs(60);
+        let claimed = store.claim(SystemTime::now(), lease, 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+
+        assert_eq!(worker.process_due().await.unwrap(), 0);
+        assert_eq!(worker.mailer.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_outbox_worker_dead_letters() {
+        let store = Arc::new(MemoryOutboxStore::new());
+        let queue = QueueMailer::new(store.clone());
+        let mailer = FlakyMailer {
+            failures: u32::MAX,
+            calls: AtomicU32::new(0),
+        };
+        let worker = OutboxWorker::new(store.clone(), mailer).retry_policy(retry_policy(2));
+
+        let id = queue.send(&message()).await.unwrap().remove(0);
+        assert_eq!(worker.process_due().await.unwrap(), 2);
+
+        let status = queue.status(&id).await.unwrap();
+        assert_eq!(status, Some(OutboxStatus::DeadLettered));
+
+        let dead_letters = store.dead_letters().await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(
+            dead_letters[0].last_error.as_deref(),
+            Some("Unexpected response: 503 - Try again later"),
+        );
+
+        assert!(queue.requeue(&id).await.unwrap());
+        assert_eq!(
+            queue.status(&id).await.unwrap(),
+            Some(OutboxStatus::Pending)
+        );
+    }
+}
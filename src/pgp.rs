@@ -0,0 +1,476 @@
+//! OpenPGP/MIME (RFC 3156) signing and encryption for messages rendered as MIME.
+//!
+//! Signatures use SHA-256, and encryption uses AES-256 (SEIPD v1, for compatibility with GnuPG).
+
+use async_trait::async_trait;
+use pgp::composed::ArmorOptions;
+use pgp::composed::Deserializable;
+use pgp::composed::DetachedSignature;
+use pgp::composed::MessageBuilder;
+use pgp::composed::SignedPublicKey;
+use pgp::composed::SignedSecretKey;
+use pgp::crypto::hash::HashAlgorithm;
+use pgp::crypto::sym::SymmetricKeyAlgorithm;
+use pgp::types::KeyDetails;
+use pgp::types::Password;
+use rand::thread_rng;
+
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::RawMailer;
+use crate::encryption;
+use crate::encryption::unexpected_error;
+use crate::mailers::reject_template;
+use crate::mime;
+
+#[derive(Clone)]
+pub struct PgpPublicKey {
+    key: SignedPublicKey,
+    // Index of the subkey to encrypt to, or `None` for the primary key
+    encryption_subkey: Option<usize>,
+}
+
+pub struct PgpSigner {
+    key: SignedSecretKey,
+    passphrase: Password,
+}
+
+/// The OpenPGP counterpart of `SmimeMailer`, encrypting for public keys instead of certificates.
+///
+/// The Bcc recipients each get a copy of their own, so that the others can't see them.
+pub struct PgpMailer<M> {
+    mailer: M,
+    signer: Option<PgpSigner>,
+    public_keys: Vec<(String, PgpPublicKey)>,
+}
+
+impl PgpPublicKey {
+    /// Uses the ASCII armored public key, which needs a key (or subkey) for encryption.
+    pub fn from_armored(armored: &str) -> Result<Self, GenericMailerError> {
+        let (key, _) = SignedPublicKey::from_string(armored).map_err(unexpected_error)?;
+        key.verify_bindings().map_err(unexpected_error)?;
+
+        let encryption_subkey = key.public_subkeys.iter().position(|subkey| {
+            return subkey.signatures.iter().any(|sig| {
+                let flags = sig.key_flags();
+                return flags.encrypt_comms() || flags.encrypt_storage();
+            });
+        });
+
+        if encryption_subkey.is_none() && !key.primary_key.algorithm().can_encrypt() {
+            let error = "OpenPGP key has no encryption key";
+            return Err(GenericMailerError::UnexpectedError(error.into()));
+        }
+
+        return Ok(Self {
+            key,
+            encryption_subkey,
+        });
+    }
+}
+
+impl PgpSigner {
+    /// Uses the ASCII armored secret key, signing with its primary key.
+    pub fn new(armored: &str, passphrase: &str) -> Result<Self, GenericMailerError> {
+        let (key, _) = SignedSecretKey::from_string(armored).map_err(unexpected_error)?;
+        key.verify_bindings().map_err(unexpected_error)?;
+
+        return Ok(Self {
+            key,
+            passphrase: passphrase.into(),
+        });
+    }
+
+    /// Wraps the rendered message in a `multipart/signed` entity, with a detached signature.
+    pub fn sign(&self, raw: &str) -> Result<String, GenericMailerError> {
+        let (headers, entity) = mime::split_entity(raw);
+        // The CRLF before the boundary belongs to the boundary, so it's not signed
+        let content = entity.strip_suffix("\r\n").unwrap_or(&entity);
+
+        let signature = DetachedSignature::sign_binary_data(
+            thread_rng(),
+            &self.key.primary_key,
+            &self.passphrase,
+            HashAlgorithm::Sha256,
+            content.as_bytes(),
+        )
+        .map_err(unexpected_error)?;
+        let armored = signature
+            .to_armored_string(ArmorOptions::default())
+            .map_err(unexpected_error)?;
+
+        let boundary = mime::generate_boundary();
+        let mut out = headers;
+
+        mime::write_multipart_start(
+            &mut out,
+            "multipart/signed; protocol=\"application/pgp-signature\"; micalg=pgp-sha256",
+            &boundary,
+        );
+
+        mime::write_boundary(&mut out, &boundary);
+        out.push_str(content);
+        out.push_str("\r\n");
+
+        mime::write_boundary(&mut out, &boundary);
+        mime::write_header(
+            &mut out,
+            "Content-Type",
+            "application/pgp-signature; name=\"signature.asc\"",
+        );
+        mime::write_header(
+            &mut out,
+            "Content-Disposition",
+            "attachment; filename=\"signature.asc\"",
+        );
+        out.push_str("\r\n");
+        write_armored(&mut out, &armored);
+
+        mime::write_multipart_end(&mut out, &boundary);
+
+        return Ok(out);
+    }
+}
+
+/// Wraps the rendered message in a `multipart/encrypted` entity,
+/// which only the holders of the public keys' secret keys can decrypt.
+pub fn encrypt(raw: &str, public_keys: &[&PgpPublicKey]) -> Result<String, GenericMailerError> {
+    let (headers, entity) = mime::split_entity(raw);
+
+    let mut builder = MessageBuilder::from_bytes("", entity.into_bytes())
+        .seipd_v1(thread_rng(), SymmetricKeyAlgorithm::AES256);
+
+    for public_key in public_keys {
+        let result = match public_key.encryption_subkey {
+            Some(i) => builder.encrypt_to_key(thread_rng(), &public_key.key.public_subkeys[i]),
+            None => builder.encrypt_to_key(thread_rng(), &public_key.key.primary_key),
+        };
+
+        result.map_err(unexpected_error)?;
+    }
+
+    let armored = builder
+        .to_armored_string(thread_rng(), ArmorOptions::default())
+        .map_err(unexpected_error)?;
+
+    let boundary = mime::generate_boundary();
+    let mut out = headers;
+
+    mime::write_multipart_start(
+        &mut out,
+        "multipart/encrypted; protocol=\"application/pgp-encrypted\"",
+        &boundary,
+    );
+
+    // See: https://datatracker.ietf.org/doc/html/rfc3156#section-4
+    mime::write_boundary(&mut out, &boundary);
+    mime::write_header(&mut out, "Content-Type", "application/pgp-encrypted");
+    mime::write_header(
+        &mut out,
+        "Content-Description",
+        "PGP/MIME version identification",
+    );
+    out.push_str("\r\nVersion: 1\r\n");
+
+    mime::write_boundary(&mut out, &boundary);
+    mime::write_header(
+        &mut out,
+        "Content-Type",
+        "application/octet-stream; name=\"encrypted.asc\"",
+    );
+    mime::write_header(
+        &mut out,
+        "Content-Disposition",
+        "inline; filename=\"encrypted.asc\"",
+    );
+    out.push_str("\r\n");
+    write_armored(&mut out, &armored);
+
+    mime::write_multipart_end(&mut out, &boundary);
+
+    return Ok(out);
+}
+
+impl<M: RawMailer> PgpMailer<M> {
+    pub fn new(mailer: M) -> Self {
+        Self {
+            mailer,
+            signer: None,
+            public_keys: Vec::new(),
+        }
+    }
+
+    pub fn signer(mut self, signer: PgpSigner) -> Self {
+        self.signer = Some(signer);
+
+        return self;
+    }
+
+    /// Adds the public key to encrypt for, when the address is the sender or one of the recipients.
+    pub fn public_key(mut self, email: impl Into<String>, public_key: PgpPublicKey) -> Self {
+        self.public_keys.push((email.into(), public_key));
+
+        return self;
+    }
+
+    async fn sign_and_send(
+        &self,
+        m: &Message<'_>,
+        raw: &str,
+    ) -> Result<Vec<String>, GenericMailerError> {
+        let raw = match &self.signer {
+            Some(signer) => signer.sign(raw)?,
+            None => raw.to_string(),
+        };
+
+        let (public_keys, kind) = (&self.public_keys, "OpenPGP key");
+
+        return encryption::send_encrypted(&self.mailer, public_keys, kind, m, &raw, encrypt).await;
+    }
+}
+
+#[async_trait]
+impl<M: RawMailer> GenericMailer for PgpMailer<M> {
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("OpenPGP", m)?;
+
+        return self.sign_and_send(m, &mime::render_message(m)).await;
+    }
+}
+
+#[async_trait]
+impl<M: RawMailer> RawMailer for PgpMailer<M> {
+    async fn send_raw(&self, m: &Message, raw: &[u8]) -> Result<Vec<String>, GenericMailerError> {
+        let raw = std::str::from_utf8(raw).map_err(unexpected_error)?;
+
+        return self.sign_and_send(m, raw).await;
+    }
+}
+
+// The armor uses bare LFs, but MIME needs CRLFs
+fn write_armored(out: &mut String, armored: &str) {
+    for line in armored.lines() {
+        out.push_str(line);
+        out.push_str("\r\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use super::*;
+
+    const SENDER_PUBLIC_KEY: &str = include_str!("../tests/fixtures/pgp/sender.pub.asc");
+    const SENDER_SECRET_KEY: &str = include_str!("../tests/fixtures/pgp/sender.sec.asc");
+    const RECIPIENT_PUBLIC_KEY: &str = include_str!("../tests/fixtures/pgp/recipient.pub.asc");
+    const RECIPIENT_SECRET_KEY: &str = include_str!("../tests/fixtures/pgp/recipient.sec.asc");
+
+    fn message() -> Message<'static> {
+        return Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("Hello, world!")
+            .html_body("<p>Hello, world!</p>")
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+    }
+
+    fn signer() -> PgpSigner {
+        return PgpSigner::new(SENDER_SECRET_KEY, "").unwrap();
+    }
+
+    fn parts(raw: &str) -> Vec<&str> {
+        let (_, boundary) = raw.split_once("boundary=\"").unwrap();
+        let (boundary, _) = boundary.split_once('"').unwrap();
+
+        return raw.split(&format!("--{boundary}")).collect();
+    }
+
+    // Checks the detached signature, returning the signed content
+    fn verify(signed: &str) -> String {
+        let parts = parts(signed);
+        let content = parts[1]
+            .strip_prefix("\r\n")
+            .unwrap()
+            .strip_suffix("\r\n")
+            .unwrap();
+        let (_, signature) = parts[2].split_once("\r\n\r\n").unwrap();
+
+        let (public_key, _) = SignedPublicKey::from_string(SENDER_PUBLIC_KEY).unwrap();
+        let (signature, _) = DetachedSignature::from_string(signature).unwrap();
+        signature.verify(&public_key, content.as_bytes()).unwrap();
+
+        return content.to_string();
+    }
+
+    fn decrypt(encrypted: &str, armored_secret_key: &str) -> String {
+        let parts = parts(encrypted);
+        assert_eq!(
+            parts[1],
+            "\r\nContent-Type: application/pgp-encrypted\r\n\
+            Content-Description: PGP/MIME version identification\r\n\
+            \r\n\
+            Version: 1\r\n"
+        );
+
+        let (_, armored) = parts[2].split_once("\r\n\r\n").unwrap();
+        let (secret_key, _) = SignedSecretKey::from_string(armored_secret_key).unwrap();
+        let (message, _) = pgp::composed::Message::from_string(armored).unwrap();
+        let mut decrypted = message.decrypt(&Password::empty(), &secret_key).unwrap();
+
+        return String::from_utf8(decrypted.as_data_vec().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_pgp_sign() {
+        let raw = mime::render_message(&message());
+        let signed = signer().sign(&raw).unwrap();
+
+        assert!(signed.starts_with(
+            "From: sender@example.com\r\n\
+            To: recipient@example.com\r\n\
+            Subject: Test Email\r\n\
+            Message-ID: <abc123@example.com>\r\n\
+            Date: Thu, 01 Jan 1970 00:00:00 +0000\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/signed; protocol=\"application/pgp-signature\"; micalg=pgp-sha256;"
+        ));
+
+        let (_, entity) = mime::split_entity(&raw);
+        let content = verify(&signed);
+        assert_eq!(format!("{content}\r\n"), entity);
+        assert!(content.starts_with("Content-Type: multipart/alternative;"));
+    }
+
+    #[test]
+    fn test_pgp_encrypt() {
+        let raw = mime::render_message(&message());
+        let public_key = PgpPublicKey::from_armored(RECIPIENT_PUBLIC_KEY).unwrap();
+        let encrypted = encrypt(&raw, &[&public_key]).unwrap();
+
+        assert!(encrypted.contains(
+            "MIME-Version: 1.0\r\n\
+            Content-Type: multipart/encrypted; protocol=\"application/pgp-encrypted\";"
+        ));
+        assert!(!encrypted.contains("Hello, world!"));
+
+        let (_, entity) = mime::split_entity(&raw);
+        assert_eq!(decrypt(&encrypted, RECIPIENT_SECRET_KEY), entity);
+    }
+
+    #[test]
+    fn test_pgp_public_key_invalid() {
+        let Err(GenericMailerError::UnexpectedError(err)) = PgpPublicKey::from_armored("invalid")
+        else {
+            panic!("expected an unexpected error");
+        };
+
+        assert!(err.downcast_ref::<pgp::errors::Error>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_pgp_mailer() {
+        struct CapturingMailer(Mutex<Vec<u8>>);
+
+        #[async_trait]
+        impl RawMailer for CapturingMailer {
+            async fn send_raw(
+                &self,
+                _: &Message,
+                raw: &[u8],
+            ) -> Result<Vec<String>, GenericMailerError> {
+                *self.0.lock().unwrap() = raw.to_vec();
+
+                return Ok(vec!["abc123".to_string()]);
+            }
+        }
+
+        let mailer = PgpMailer::new(CapturingMailer(Mutex::new(Vec::new())))
+            .signer(signer())
+            .public_key(
+                "Recipient@Example.com",
+                PgpPublicKey::from_armored(RECIPIENT_PUBLIC_KEY).unwrap(),
+            )
+            .public_key(
+                "sender@example.com",
+                PgpPublicKey::from_armored(SENDER_PUBLIC_KEY).unwrap(),
+            );
+
+        let ids = mailer.send(&message()).await.unwrap();
+        assert_eq!(ids, ["abc123"]);
+
+        // Signed, then encrypted for both the recipient and the sender
+        let raw = String::from_utf8(mailer.mailer.0.lock().unwrap().clone()).unwrap();
+        for secret_key in [RECIPIENT_SECRET_KEY, SENDER_SECRET_KEY] {
+            let decrypted = decrypt(&raw, secret_key);
+            assert!(decrypted.starts_with("Content-Type: multipart/signed;"));
+            verify(&decrypted);
+        }
+
+        let mut message = message();
+        message.cc.push("unknown@example.com".into());
+
+        let err = mailer.send(&message).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected error: no OpenPGP key for unknown@example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pgp_mailer_bcc() {
+        // Records the envelope recipients of each copy
+        struct CapturingMailer(Mutex<Vec<(Vec<String>, String)>>);
+
+        #[async_trait]
+        impl RawMailer for CapturingMailer {
+            async fn send_raw(
+                &self,
+                m: &Message,
+                raw: &[u8],
+            ) -> Result<Vec<String>, GenericMailerError> {
+                let recipients = m.to.iter().chain(&m.cc).chain(&m.bcc);
+                let recipients = recipients.map(|a| a.email.to_string()).collect();
+                let raw = String::from_utf8(raw.to_vec()).unwrap();
+                self.0.lock().unwrap().push((recipients, raw));
+
+                return Ok(Vec::new());
+            }
+        }
+
+        let mailer = PgpMailer::new(CapturingMailer(Mutex::new(Vec::new())))
+            .public_key(
+                "recipient@example.com",
+                PgpPublicKey::from_armored(RECIPIENT_PUBLIC_KEY).unwrap(),
+            )
+            .public_key(
+                "bcc@example.com",
+                PgpPublicKey::from_armored(SENDER_PUBLIC_KEY).unwrap(),
+            );
+
+        let mut message = message();
+        message.bcc.push("bcc@example.com".into());
+        mailer.send(&message).await.unwrap();
+
+        // The Bcc recipient gets a copy of their own, which the others can't decrypt
+        let copies = mailer.mailer.0.lock().unwrap().clone();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[0].0, ["recipient@example.com"]);
+        assert_eq!(copies[1].0, ["bcc@example.com"]);
+
+        let decrypted = decrypt(&copies[0].1, RECIPIENT_SECRET_KEY);
+        assert!(decrypted.starts_with("Content-Type: multipart/alternative;"));
+        assert_eq!(decrypt(&copies[1].1, SENDER_SECRET_KEY), decrypted);
+
+        let (_, armored) = parts(&copies[0].1)[2].split_once("\r\n\r\n").unwrap();
+        let (secret_key, _) = SignedSecretKey::from_string(SENDER_SECRET_KEY).unwrap();
+        let (encrypted, _) = pgp::composed::Message::from_string(armored).unwrap();
+        assert!(encrypted.decrypt(&Password::empty(), &secret_key).is_err());
+    }
+}
@@ -0,0 +1,574 @@
+//! S/MIME (RFC 8551) signing and encryption for messages rendered as MIME.
+//!
+//! Only RSA keys are supported. Signatures use SHA-256, and encryption uses AES-256-CBC.
+
+use async_trait::async_trait;
+use cms::builder::ContentEncryptionAlgorithm;
+use cms::builder::EnvelopedDataBuilder;
+use cms::builder::KeyEncryptionInfo;
+use cms::builder::KeyTransRecipientInfoBuilder;
+use cms::builder::SignedDataBuilder;
+use cms::builder::SignerInfoBuilder;
+use cms::builder::create_signing_time_attribute;
+use cms::cert::CertificateChoices;
+use cms::cert::IssuerAndSerialNumber;
+use cms::content_info::ContentInfo;
+use cms::enveloped_data::RecipientIdentifier;
+use cms::signed_data::EncapsulatedContentInfo;
+use cms::signed_data::SignerIdentifier;
+use rsa::RsaPrivateKey;
+use rsa::RsaPublicKey;
+use rsa::pkcs1v15::Signature;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::rand_core::OsRng;
+use sha2::Digest;
+use sha2::Sha256;
+use x509_cert::Certificate;
+use x509_cert::der::Any;
+use x509_cert::der::DecodePem;
+use x509_cert::der::Encode;
+use x509_cert::der::oid::db::rfc5911;
+use x509_cert::der::oid::db::rfc5912;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+use crate::GenericMailer;
+use crate::GenericMailerError;
+use crate::Message;
+use crate::RawMailer;
+use crate::encryption;
+use crate::encryption::unexpected_error;
+use crate::mailers::reject_template;
+use crate::mime;
+
+#[derive(Clone)]
+pub struct SmimeCertificate {
+    certificate: Certificate,
+    public_key: RsaPublicKey,
+}
+
+pub struct SmimeSigner {
+    certificate: SmimeCertificate,
+    signing_key: SigningKey<Sha256>,
+}
+
+/// Signs and/or encrypts messages before passing them on to a raw transport, e.g. `AwsSesMailer`.
+///
+/// Once a certificate is added, every message gets encrypted,
+/// and sending fails if one of the recipients doesn't have a certificate.
+/// The Bcc recipients each get a copy of their own, so that the others can't see them.
+pub struct SmimeMailer<M> {
+    mailer: M,
+    signer: Option<SmimeSigner>,
+    certificates: Vec<(String, SmimeCertificate)>,
+}
+
+impl SmimeCertificate {
+    pub fn from_pem(pem: &str) -> Result<Self, GenericMailerError> {
+        let certificate = Certificate::from_pem(pem).map_err(unexpected_error)?;
+
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(unexpected_error)?;
+        let public_key = RsaPublicKey::from_public_key_der(&spki).map_err(unexpected_error)?;
+
+        return Ok(Self {
+            certificate,
+            public_key,
+        });
+    }
+
+    fn issuer_and_serial_number(&self) -> IssuerAndSerialNumber {
+        return IssuerAndSerialNumber {
+            issuer: self.certificate.tbs_certificate.issuer.clone(),
+            serial_number: self.certificate.tbs_certificate.serial_number.clone(),
+        };
+    }
+}
+
+impl SmimeSigner {
+    /// Uses the PEM encoded certificate and its (PKCS #8) private key.
+    pub fn new(certificate_pem: &str, private_key_pem: &str) -> Result<Self, GenericMailerError> {
+        let certificate = SmimeCertificate::from_pem(certificate_pem)?;
+        let private_key =
+            RsaPrivateKey::from_pkcs8_pem(private_key_pem).map_err(unexpected_error)?;
+
+        return Ok(Self {
+            certificate,
+            signing_key: SigningKey::new(private_key),
+        });
+    }
+
+    /// Wraps the rendered message in a `multipart/signed` entity, with a detached signature.
+    pub fn sign(&self, raw: &str) -> Result<String, GenericMailerError> {
+        let (headers, entity) = mime::split_entity(raw);
+
+        // The CRLF before the boundary belongs to the boundary, so it's not signed
+        let content = entity.strip_suffix("\r\n").unwrap_or(&entity);
+        let signature = self.build_signature(content.as_bytes())?;
+
+        let boundary = mime::generate_boundary();
+        let mut out = headers;
+
+        mime::write_multipart_start(
+            &mut out,
+            "multipart/signed; protocol=\"application/pkcs7-signature\"; micalg=sha-256",
+            &boundary,
+        );
+
+        mime::write_boundary(&mut out, &boundary);
+        out.push_str(content);
+        out.push_str("\r\n");
+
+        mime::write_boundary(&mut out, &boundary);
+        write_pkcs7_headers(&mut out, "application/pkcs7-signature", "smime.p7s");
+        mime::write_base64(&mut out, &signature);
+
+        mime::write_multipart_end(&mut out, &boundary);
+
+        return Ok(out);
+    }
+
+    // See: https://datatracker.ietf.org/doc/html/rfc8551#section-3.5.3
+    fn build_signature(&self, content: &[u8]) -> Result<Vec<u8>, GenericMailerError> {
+        let digest = Sha256::digest(content);
+        let digest_algorithm = AlgorithmIdentifierOwned {
+            oid: rfc5912::ID_SHA_256,
+            parameters: None,
+        };
+
+        // Detached, the content is the first part of the `multipart/signed` entity
+        let content_info = EncapsulatedContentInfo {
+            econtent_type: rfc5911::ID_DATA,
+            econtent: None,
+        };
+
+        let sid =
+            SignerIdentifier::IssuerAndSerialNumber(self.certificate.issuer_and_serial_number());
+        let mut signer_info = SignerInfoBuilder::new(
+            &self.signing_key,
+            sid,
+            digest_algorithm.clone(),
+            &content_info,
+            Some(&digest),
+        )
+        .map_err(builder_error)?;
+        signer_info
+            .add_signed_attribute(create_signing_time_attribute().map_err(builder_error)?)
+            .map_err(builder_error)?;
+
+        let signed_data = SignedDataBuilder::new(&content_info)
+            .add_digest_algorithm(digest_algorithm)
+            .map_err(builder_error)?
+            .add_certificate(CertificateChoices::Certificate(
+                self.certificate.certificate.clone(),
+            ))
+            .map_err(builder_error)?
+            .add_signer_info::<SigningKey<Sha256>, Signature>(signer_info)
+            .map_err(builder_error)?
+            .build()
+            .map_err(builder_error)?;
+
+        return signed_data.to_der().map_err(unexpected_error);
+    }
+}
+
+/// Wraps the rendered message in an `application/pkcs7-mime` entity,
+/// which only the holders of the certificates' private keys can decrypt.
+pub fn encrypt(
+    raw: &str,
+    certificates: &[&SmimeCertificate],
+) -> Result<String, GenericMailerError> {
+    let (headers, entity) = mime::split_entity(raw);
+
+    // Each recipient needs its own handle on the RNG
+    let mut rngs = vec![OsRng; certificates.len()];
+
+    let mut builder = EnvelopedDataBuilder::new(
+        None,
+        entity.as_bytes(),
+        ContentEncryptionAlgorithm::Aes256Cbc,
+        None,
+    )
+    .map_err(builder_error)?;
+
+    for (certificate, rng) in certificates.iter().zip(rngs.iter_mut()) {
+        let rid =
+            RecipientIdentifier::IssuerAndSerialNumber(certificate.issuer_and_serial_number());
+        let key = KeyEncryptionInfo::Rsa(certificate.public_key.clone());
+        let recipient_info =
+            KeyTransRecipientInfoBuilder::new(rid, key, rng).map_err(builder_error)?;
+
+        builder
+            .add_recipient_info(recipient_info)
+            .map_err(builder_error)?;
+    }
+
+    let enveloped_data = builder.build_with_rng(&mut OsRng).map_err(builder_error)?;
+    let content_info = ContentInfo {
+        content_type: rfc5911::ID_ENVELOPED_DATA,
+        content: Any::encode_from(&enveloped_data).map_err(unexpected_error)?,
+    };
+    let der = content_info.to_der().map_err(unexpected_error)?;
+
+    let mut out = headers;
+    write_pkcs7_headers(
+        &mut out,
+        "application/pkcs7-mime; smime-type=enveloped-data",
+        "smime.p7m",
+    );
+    mime::write_base64(&mut out, &der);
+
+    return Ok(out);
+}
+
+impl<M: RawMailer> SmimeMailer<M> {
+    pub fn new(mailer: M) -> Self {
+        Self {
+            mailer,
+            signer: None,
+            certificates: Vec::new(),
+        }
+    }
+
+    pub fn signer(mut self, signer: SmimeSigner) -> Self {
+        self.signer = Some(signer);
+
+        return self;
+    }
+
+    /// Adds the certificate to encrypt for, when the address is the sender or one of the recipients.
+    pub fn certificate(mut self, email: impl Into<String>, certificate: SmimeCertificate) -> Self {
+        self.certificates.push((email.into(), certificate));
+
+        return self;
+    }
+
+    async fn sign_and_send(
+        &self,
+        m: &Message<'_>,
+        raw: &str,
+    ) -> Result<Vec<String>, GenericMailerError> {
+        let raw = match &self.signer {
+            Some(signer) => signer.sign(raw)?,
+            None => raw.to_string(),
+        };
+
+        let (certificates, kind) = (&self.certificates, "S/MIME certificate");
+
+        return encryption::send_encrypted(&self.mailer, certificates, kind, m, &raw, encrypt)
+            .await;
+    }
+}
+
+#[async_trait]
+impl<M: RawMailer> GenericMailer for SmimeMailer<M> {
+    async fn send(&self, m: &Message) -> Result<Vec<String>, GenericMailerError> {
+        reject_template("S/MIME", m)?;
+
+        return self.sign_and_send(m, &mime::render_message(m)).await;
+    }
+}
+
+#[async_trait]
+impl<M: RawMailer> RawMailer for SmimeMailer<M> {
+    async fn send_raw(&self, m: &Message, raw: &[u8]) -> Result<Vec<String>, GenericMailerError> {
+        let raw = std::str::from_utf8(raw).map_err(unexpected_error)?;
+
+        return self.sign_and_send(m, raw).await;
+    }
+}
+
+fn write_pkcs7_headers(out: &mut String, content_type: &str, filename: &str) {
+    mime::write_header(
+        out,
+        "Content-Type",
+        &format!("{content_type}; name=\"{filename}\""),
+    );
+    mime::write_header(out, "Content-Transfer-Encoding", "base64");
+    mime::write_header(
+        out,
+        "Content-Disposition",
+        &format!("attachment; filename=\"{filename}\""),
+    );
+    out.push_str("\r\n");
+}
+
+// The CMS builder's error doesn't implement `Error`, so the error it wraps is passed on where possible
+fn builder_error(err: cms::builder::Error) -> GenericMailerError {
+    return match err {
+        cms::builder::Error::Asn1(err) => unexpected_error(err),
+        cms::builder::Error::PublicKey(err) => unexpected_error(err),
+        cms::builder::Error::Signature(err) => unexpected_error(err),
+        err => GenericMailerError::UnexpectedError(err.to_string().into()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use aes::Aes256;
+    use base64::Engine as _;
+    use base64::prelude::BASE64_STANDARD;
+    use cbc::cipher::BlockDecryptMut;
+    use cbc::cipher::KeyIvInit;
+    use cbc::cipher::block_padding::Pkcs7;
+    use cms::enveloped_data::EnvelopedData;
+    use cms::enveloped_data::RecipientInfo;
+    use cms::signed_data::SignedData;
+    use rsa::Pkcs1v15Encrypt;
+    use rsa::pkcs1v15::VerifyingKey;
+    use rsa::signature::Verifier;
+    use x509_cert::der::Decode;
+    use x509_cert::der::asn1::OctetString;
+
+    use super::*;
+
+    const SENDER_CERTIFICATE: &str = include_str!("../tests/fixtures/smime/sender.crt");
+    const SENDER_KEY: &str = include_str!("../tests/fixtures/smime/sender.key");
+    const RECIPIENT_CERTIFICATE: &str = include_str!("../tests/fixtures/smime/recipient.crt");
+    const RECIPIENT_KEY: &str = include_str!("../tests/fixtures/smime/recipient.key");
+
+    fn message() -> Message<'static> {
+        return Message::builder()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .subject("Test Email")
+            .text_body("Hello, world!")
+            .html_body("<p>Hello, world!</p>")
+            .message_id("<abc123@example.com>")
+            .date(SystemTime::UNIX_EPOCH)
+            .build()
+            .unwrap();
+    }
+
+    fn signer() -> SmimeSigner {
+        return SmimeSigner::new(SENDER_CERTIFICATE, SENDER_KEY).unwrap();
+    }
+
+    fn decode_base64_lines(s: &str) -> Vec<u8> {
+        return BASE64_STANDARD.decode(s.replace("\r\n", "")).unwrap();
+    }
+
+    // Checks the detached signature, returning the signed content
+    fn verify(signed: &str) -> String {
+        let (_, boundary) = signed.split_once("boundary=\"").unwrap();
+        let (boundary, _) = boundary.split_once('"').unwrap();
+
+        let parts: Vec<_> = signed.split(&format!("--{boundary}")).collect();
+        let content = parts[1]
+            .strip_prefix("\r\n")
+            .unwrap()
+            .strip_suffix("\r\n")
+            .unwrap();
+        let (_, signature) = parts[2].split_once("\r\n\r\n").unwrap();
+
+        let content_info = ContentInfo::from_der(&decode_base64_lines(signature)).unwrap();
+        let signed_data = content_info.content.decode_as::<SignedData>().unwrap();
+        let signer_info = signed_data.signer_infos.0.get(0).unwrap();
+        let signed_attrs = signer_info.signed_attrs.as_ref().unwrap();
+
+        let digest = signed_attrs
+            .iter()
+            .find(|attr| attr.oid == rfc5911::ID_MESSAGE_DIGEST)
+            .map(|attr| {
+                attr.values
+                    .get(0)
+                    .unwrap()
+                    .decode_as::<OctetString>()
+                    .unwrap()
+            })
+            .unwrap();
+        assert_eq!(
+            digest.as_bytes(),
+            Sha256::digest(content.as_bytes()).as_slice()
+        );
+
+        let public_key = SmimeCertificate::from_pem(SENDER_CERTIFICATE)
+            .unwrap()
+            .public_key;
+        let signature = Signature::try_from(signer_info.signature.as_bytes()).unwrap();
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(&signed_attrs.to_der().unwrap(), &signature)
+            .unwrap();
+
+        return content.to_string();
+    }
+
+    fn decrypt(encrypted: &str, private_key_pem: &str) -> String {
+        let (_, body) = encrypted.split_once("\r\n\r\n").unwrap();
+
+        let content_info = ContentInfo::from_der(&decode_base64_lines(body)).unwrap();
+        let enveloped_data = content_info.content.decode_as::<EnvelopedData>().unwrap();
+
+        let private_key = RsaPrivateKey::from_pkcs8_pem(private_key_pem).unwrap();
+        let key = enveloped_data
+            .recip_infos
+            .0
+            .iter()
+            .find_map(|info| match info {
+                RecipientInfo::Ktri(ktri) => private_key
+                    .decrypt(Pkcs1v15Encrypt, ktri.enc_key.as_bytes())
+                    .ok(),
+                _ => None,
+            })
+            .unwrap();
+
+        let content = &enveloped_data.encrypted_content;
+        let iv = content.content_enc_alg.parameters.as_ref().unwrap();
+        let iv = iv.decode_as::<OctetString>().unwrap();
+
+        let decrypted = cbc::Decryptor::<Aes256>::new_from_slices(&key, iv.as_bytes())
+            .unwrap()
+            .decrypt_padded_vec_mut::<Pkcs7>(content.encrypted_content.as_ref().unwrap().as_bytes())
+            .unwrap();
+
+        return String::from_utf8(decrypted).unwrap();
+    }
+
+    #[test]
+    fn test_smime_sign() {
+        let raw = mime::render_message(&message());
+        let signed = signer().sign(&raw).unwrap();
+
+        assert!(signed.starts_with(
+            "From: sender@example.com\r\n\
+            To: recipient@example.com\r\n\
+            Subject: Test Email\r\n\
+            Message-ID: <abc123@example.com>\r\n\
+            Date: Thu, 01 Jan 1970 00:00:00 +0000\r\n\
+            MIME-Version: 1.0\r\n\
+            Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\"; micalg=sha-256;"
+        ));
+
+        let (_, entity) = mime::split_entity(&raw);
+        let content = verify(&signed);
+        assert_eq!(format!("{content}\r\n"), entity);
+        assert!(content.starts_with("Content-Type: multipart/alternative;"));
+    }
+
+    #[test]
+    fn test_smime_encrypt() {
+        let raw = mime::render_message(&message());
+        let certificate = SmimeCertificate::from_pem(RECIPIENT_CERTIFICATE).unwrap();
+        let encrypted = encrypt(&raw, &[&certificate]).unwrap();
+
+        assert!(encrypted.contains(
+            "MIME-Version: 1.0\r\n\
+            Content-Type: application/pkcs7-mime; smime-type=enveloped-data; name=\"smime.p7m\"\r\n"
+        ));
+        assert!(!encrypted.contains("Hello, world!"));
+
+        let (_, entity) = mime::split_entity(&raw);
+        assert_eq!(decrypt(&encrypted, RECIPIENT_KEY), entity);
+    }
+
+    #[tokio::test]
+    async fn test_smime_mailer() {
+        struct CapturingMailer(Mutex<Vec<u8>>);
+
+        #[async_trait]
+        impl RawMailer for CapturingMailer {
+            async fn send_raw(
+                &self,
+                _: &Message,
+                raw: &[u8],
+            ) -> Result<Vec<String>, GenericMailerError> {
+                *self.0.lock().unwrap() = raw.to_vec();
+
+                return Ok(vec!["abc123".to_string()]);
+            }
+        }
+
+        let mailer = SmimeMailer::new(CapturingMailer(Mutex::new(Vec::new())))
+            .signer(signer())
+            .certificate(
+                "Recipient@Example.com",
+                SmimeCertificate::from_pem(RECIPIENT_CERTIFICATE).unwrap(),
+            )
+            .certificate(
+                "sender@example.com",
+                SmimeCertificate::from_pem(SENDER_CERTIFICATE).unwrap(),
+            );
+
+        let ids = mailer.send(&message()).await.unwrap();
+        assert_eq!(ids, ["abc123"]);
+
+        // Signed, then encrypted for both the recipient and the sender
+        let raw = String::from_utf8(mailer.mailer.0.lock().unwrap().clone()).unwrap();
+        for key in [RECIPIENT_KEY, SENDER_KEY] {
+            let decrypted = decrypt(&raw, key);
+            assert!(decrypted.starts_with("Content-Type: multipart/signed;"));
+            verify(&decrypted);
+        }
+
+        let mut message = message();
+        message.cc.push("unknown@example.com".into());
+
+        let err = mailer.send(&message).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected error: no S/MIME certificate for unknown@example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_smime_mailer_bcc() {
+        // Records the envelope recipients of each copy
+        struct CapturingMailer(Mutex<Vec<(Vec<String>, String)>>);
+
+        #[async_trait]
+        impl RawMailer for CapturingMailer {
+            async fn send_raw(
+                &self,
+                m: &Message,
+                raw: &[u8],
+            ) -> Result<Vec<String>, GenericMailerError> {
+                let recipients = m.to.iter().chain(&m.cc).chain(&m.bcc);
+                let recipients = recipients.map(|a| a.email.to_string()).collect();
+                let raw = String::from_utf8(raw.to_vec()).unwrap();
+                self.0.lock().unwrap().push((recipients, raw));
+
+                return Ok(Vec::new());
+            }
+        }
+
+        let mailer = SmimeMailer::new(CapturingMailer(Mutex::new(Vec::new())))
+            .certificate(
+                "recipient@example.com",
+                SmimeCertificate::from_pem(RECIPIENT_CERTIFICATE).unwrap(),
+            )
+            .certificate(
+                "bcc@example.com",
+                SmimeCertificate::from_pem(SENDER_CERTIFICATE).unwrap(),
+            );
+
+        let mut message = message();
+        message.cc.push("recipient@example.com".into());
+        message.bcc.push("bcc@example.com".into());
+        mailer.send(&message).await.unwrap();
+
+        // The Bcc recipient gets a copy of their own, and each copy has a single key
+        let copies = mailer.mailer.0.lock().unwrap().clone();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(
+            copies[0].0,
+            ["recipient@example.com", "recipient@example.com"]
+        );
+        assert_eq!(copies[1].0, ["bcc@example.com"]);
+
+        for ((_, raw), key) in copies.iter().zip([RECIPIENT_KEY, SENDER_KEY]) {
+            let (_, body) = raw.split_once("\r\n\r\n").unwrap();
+            let content_info = ContentInfo::from_der(&decode_base64_lines(body)).unwrap();
+            let enveloped_data = content_info.content.decode_as::<EnvelopedData>().unwrap();
+            assert_eq!(enveloped_data.recip_infos.0.len(), 1);
+
+            assert!(decrypt(raw, key).starts_with("Content-Type: multipart/alternative;"));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use minijinja::Environment;
+use minijinja::ErrorKind;
+use minijinja::Value;
+
+use super::RenderedTemplate;
+use super::TemplateError;
+use super::TemplateRenderer;
+
+/// Renders templates from a directory, where each template is a subdirectory with:
+///
+/// - `subject.txt` (required)
+/// - `text.txt` and/or `html.html`
+///
+/// Following the minijinja defaults, only the `.html` files are auto-escaped.
+///
+/// ```text
+/// templates/
+///   welcome/
+///     subject.txt
+///     text.txt
+///     html.html
+/// ```
+pub struct MiniJinjaRenderer {
+    env: Environment<'static>,
+}
+
+impl MiniJinjaRenderer {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let mut env = Environment::new();
+        env.set_loader(minijinja::path_loader(dir));
+
+        return Self::with_env(env);
+    }
+
+    /// Uses an existing environment, e.g. one with custom filters or another loader.
+    pub fn with_env(env: Environment<'static>) -> Self {
+        Self { env }
+    }
+
+    pub fn env_mut(&mut self) -> &mut Environment<'static> {
+        return &mut self.env;
+    }
+
+    fn render_part(
+        &self,
+        name: &str,
+        part: &str,
+        context: &Value,
+    ) -> Result<Option<String>, TemplateError> {
+        let template = match self.env.get_template(&format!("{name}/{part}")) {
+            Ok(template) => template,
+            Err(err) if err.kind() == ErrorKind::TemplateNotFound => return Ok(None),
+            Err(err) => return Err(TemplateError::RenderError(Box::new(err))),
+        };
+
+        let rendered = template
+            .render(context)
+            .map_err(|err| TemplateError::RenderError(Box::new(err)))?;
+
+        return Ok(Some(rendered));
+    }
+}
+
+impl TemplateRenderer for MiniJinjaRenderer {
+    type Context = Value;
+
+    fn render(&self, name: &str, context: &Value) -> Result<RenderedTemplate, TemplateError> {
+        let Some(subject) = self.render_part(name, "subject.txt", context)? else {
+            return Err(TemplateError::NotFound(name.to_string()));
+        };
+
+        return Ok(RenderedTemplate {
+            // Template files usually end with a newline, which isn't allowed in the subject
+            subject: subject.trim().to_string(),
+            text_body: self.render_part(name, "text.txt", context)?,
+            html_body: self.render_part(name, "html.html", context)?,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::context;
+
+    use super::*;
+
+    fn renderer() -> MiniJinjaRenderer {
+        return MiniJinjaRenderer::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/templates"
+        ));
+    }
+
+    #[test]
+    fn test_minijinja_renderer() {
+        let context = context! { name => "<Ann>", product => "Gen Mailer" };
+        let actual = renderer().render("welcome", &context).unwrap();
+
+        let expected = RenderedTemplate {
+            subject: "Welcome, <Ann>!".to_string(),
+            text_body: Some("Hi <Ann>,\n\nThanks for signing up to Gen Mailer.".to_string()),
+            html_body: Some(
+                "<p>Hi &lt;Ann&gt;,</p>\n<p>Thanks for signing up to <strong>Gen Mailer</strong>.</p>"
+                    .to_string(),
+            ),
+        };
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_minijinja_renderer_builder() {
+        let message = renderer()
+            .builder("reminder", &context! { event => "the launch" })
+            .unwrap()
+            .from("sender@example.com")
+            .to("recipient@example.com")
+            .build()
+            .unwrap();
+
+        assert_eq!(message.subject, "Reminder: the launch");
+        assert_eq!(
+            message.text_body.as_deref(),
+            Some("Don't forget about the launch.")
+        );
+        assert_eq!(message.html_body, None);
+    }
+
+    #[test]
+    fn test_minijinja_renderer_not_found() {
+        let result = renderer().render("missing", &context! {});
+
+        assert!(matches!(result, Err(TemplateError::NotFound(name)) if name == "missing"));
+    }
+}
@@ -0,0 +1,63 @@
+//! Renders messages locally from templates, before they're sent by a mailer.
+//!
+//! Unlike `MessageTemplate`, which refers to a template stored by the provider,
+//! these fill in the subject and body of the message directly.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::MessageBuilder;
+
+#[cfg(feature = "minijinja")]
+mod jinja;
+#[cfg(feature = "minijinja")]
+pub use jinja::MiniJinjaRenderer;
+
+/// Renders the named template with the given context.
+pub trait TemplateRenderer {
+    type Context: ?Sized;
+
+    fn render(
+        &self,
+        name: &str,
+        context: &Self::Context,
+    ) -> Result<RenderedTemplate, TemplateError>;
+
+    /// Renders the template into a `MessageBuilder`, leaving the rest (e.g. `from`, `to`) to be set.
+    fn builder(
+        &self,
+        name: &str,
+        context: &Self::Context,
+    ) -> Result<MessageBuilder<'static>, TemplateError> {
+        let rendered = self.render(name, context)?;
+
+        return Ok(MessageBuilder::new()
+            .subject(rendered.subject)
+            .set_text_body(rendered.text_body)
+            .set_html_body(rendered.html_body));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedTemplate {
+    pub subject: String,
+    pub text_body: Option<String>,
+    pub html_body: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    NotFound(String),
+    RenderError(Box<dyn Error>),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            TemplateError::NotFound(name) => write!(f, "Template not found: {name}"),
+            TemplateError::RenderError(error) => write!(f, "Failed to render template: {error}"),
+        };
+    }
+}
+
+impl Error for TemplateError {}
@@ -1,5 +1,8 @@
+use std::time::SystemTime;
+
 use base64::Engine as _;
 use base64::prelude::BASE64_STANDARD;
+use uuid::Uuid;
 
 // MIME B-encoding (RFC 2047)
 // See: https://en.wikipedia.org/wiki/MIME#Encoded-Word
@@ -15,3 +18,100 @@ pub fn encode_mime_b(s: &str) -> String {
 
     return result;
 }
+
+// Date in the IMF-fixdate format used by HTTP (RFC 7231, section 7.1.1.1)
+// e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+pub fn format_http_date(time: SystemTime) -> String {
+    return format_date(time, "GMT");
+}
+
+// Date in the format used by the `Date` header (RFC 5322, section 3.3)
+// e.g. "Sun, 06 Nov 1994 08:49:37 +0000"
+pub fn format_email_date(time: SystemTime) -> String {
+    return format_date(time, "+0000");
+}
+
+// A globally unique `Message-ID` (RFC 5322, section 3.6.4)
+// e.g. "<67e55044-10b1-426f-9247-bb680e5fe0c8@example.com>"
+pub fn generate_message_id(domain: &str) -> String {
+    return format!("<{}@{domain}>", Uuid::new_v4());
+}
+
+// The domain part of the email address, e.g. for generating a `Message-ID`
+pub fn email_domain(email: &str) -> &str {
+    return email
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain);
+}
+
+fn format_date(time: SystemTime, zone: &str) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let days = secs / 86400;
+    let (year, month, day) = civil_from_days(days);
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize]; // 1970-01-01 was a Thursday
+    let month = MONTHS[(month - 1) as usize];
+    let (hour, minute, second) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+
+    return format!("{weekday}, {day:02} {month} {year} {hour:02}:{minute:02}:{second:02} {zone}");
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// Converts days since the Unix epoch to a (year, month, day) date
+// See: https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return (year, month, day);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_http_date() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(951782400);
+        assert_eq!(format_http_date(time), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn test_format_email_date() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(format_email_date(time), "Sun, 06 Nov 1994 08:49:37 +0000");
+    }
+
+    #[test]
+    fn test_generate_message_id() {
+        let id = generate_message_id("example.com");
+        let uuid = id
+            .strip_prefix('<')
+            .unwrap()
+            .strip_suffix("@example.com>")
+            .unwrap();
+
+        assert!(Uuid::parse_str(uuid).is_ok());
+        assert_ne!(id, generate_message_id("example.com"));
+    }
+}